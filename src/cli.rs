@@ -0,0 +1,509 @@
+use std::{error::Error, ops::Range, path::PathBuf, str::FromStr};
+
+use comp_math_lab4::{
+    methods::{Criterion, Family, FitOptions, NumberStyle},
+    TNumber,
+};
+
+use crate::{
+    input::{self, CsvOptions},
+    plot::{self, PlotColor, PlotFormat, PlotOptions, PlotSize},
+};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum InputFormat {
+    #[default]
+    Json,
+    Csv,
+    /// One JSON point object per line
+    Ndjson,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(InputFormat::Json),
+            "csv" => Ok(InputFormat::Csv),
+            "ndjson" => Ok(InputFormat::Ndjson),
+            _ => Err(format!(
+                "unknown input format `{}`, expected one of: json, csv, ndjson",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    /// Per-point rows of the best function, for spreadsheets
+    Csv,
+    /// Single line with coefficients of the best function and no informational messages
+    Quiet,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "quiet" => Ok(OutputFormat::Quiet),
+            _ => Err(format!(
+                "unknown output format `{}`, expected one of: table, json, csv, quiet",
+                s
+            )),
+        }
+    }
+}
+
+/// Everything that configures a run, parsed once from the command line.
+///
+/// Fitting and plotting get their own parts, [`FitOptions`] and [`PlotOptions`]
+#[derive(Debug)]
+pub struct RunConfig {
+    pub format: InputFormat,
+    pub output: OutputFormat,
+    pub csv: CsvOptions,
+    /// Read points from these files instead of stdin, each is fitted on its own
+    pub paths: Vec<PathBuf>,
+    /// Decimal places of printed numbers
+    pub precision: usize,
+    pub fit: FitOptions,
+    pub plot: PlotOptions,
+    /// Skip generating the image
+    pub no_plot: bool,
+    /// Runner-up within this fraction of the best standard deviation makes the choice ambiguous,
+    /// see [`Criterion::is_ambiguous`] for `--criterion aic`
+    pub ambiguity_threshold: TNumber,
+    pub criterion: Criterion,
+    /// Print how long each stage takes to stderr
+    pub timing: bool,
+    /// Write gnuplot friendly data of the best fit to this file
+    pub dump_fit: Option<PathBuf>,
+    /// Evaluate the best function at these x
+    pub predict: Vec<TNumber>,
+    /// Print the slope of the best function at these x
+    pub slope_at: Vec<TNumber>,
+    /// Integrate the best function from `start` to `end`
+    pub integrate: Option<Range<TNumber>>,
+    /// Print normal equations of every polynomial family to stderr
+    pub debug_matrix: bool,
+    /// Fit this many generated noisy points of a known line instead of reading input
+    pub seed_points: Option<usize>,
+    /// Draw residuals of the best fit to this image, regardless of `no_plot`
+    pub residual_plot: Option<PathBuf>,
+    /// Points with the largest residuals to list after the table
+    pub top_errors: usize,
+    /// Fit only this family and report it without comparing to others
+    pub only_family: Option<Family>,
+    /// Print residual rows in order of x instead of the input order
+    pub sort_x: bool,
+    /// Scientific notation for coefficients and table values
+    pub scientific: bool,
+    /// Add `ε/y` column to the residual table
+    pub relative: bool,
+    /// Print condition numbers of polynomial normal equations to stderr
+    pub condition_number: bool,
+    /// Print smoothed points instead of fitting, see [`comp_math_lab4::methods::smooth`]
+    pub smooth_window: Option<usize>,
+    pub smooth_degree: usize,
+    /// Tables are drawn with plain `+`, `-` and `|`
+    pub ascii: bool,
+    /// Fit x as a function of y, points are swapped back on the plots
+    pub swap_axes: bool,
+    /// Second dataset fitted and drawn on the same plot as the input
+    pub compare: Option<PathBuf>,
+    /// Print the available families and exit
+    pub list_families: bool,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            format: InputFormat::default(),
+            output: OutputFormat::default(),
+            csv: CsvOptions::default(),
+            paths: Vec::new(),
+            precision: 4,
+            fit: FitOptions::default(),
+            plot: PlotOptions::default(),
+            no_plot: false,
+            ambiguity_threshold: 0.01,
+            criterion: Criterion::default(),
+            timing: false,
+            dump_fit: None,
+            predict: Vec::new(),
+            integrate: None,
+            slope_at: Vec::new(),
+            debug_matrix: false,
+            seed_points: None,
+            residual_plot: None,
+            top_errors: 3,
+            only_family: None,
+            sort_x: false,
+            scientific: false,
+            relative: false,
+            condition_number: false,
+            smooth_window: None,
+            smooth_degree: 2,
+            ascii: false,
+            swap_axes: false,
+            compare: None,
+            list_families: false,
+        }
+    }
+}
+
+impl RunConfig {
+    /// `--precision` and `--sci` for formulas printed outside of the table
+    pub fn number_style(&self) -> NumberStyle {
+        NumberStyle {
+            precision: Some(self.precision),
+            scientific: self.scientific,
+        }
+    }
+
+    /// Chained setters starting from [`RunConfig::default`]
+    pub fn builder() -> RunConfigBuilder {
+        RunConfigBuilder::default()
+    }
+
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<RunConfig, Box<dyn Error>> {
+        let mut builder = RunConfig::builder();
+
+        while let Some(arg) = args.next() {
+            builder = match arg.as_str() {
+                "--format" => builder.format(next_value(&mut args, &arg)?.parse()?),
+                "--output" => builder.output(next_value(&mut args, &arg)?.parse()?),
+                "--quiet" => builder.output(OutputFormat::Quiet),
+                "--delimiter" => {
+                    builder.delimiter(Some(input::parse_delimiter(&next_value(&mut args, &arg)?)?))
+                }
+                "--decimal-comma" => builder.decimal_comma(true),
+                "--precision" => builder.precision(next_count(&mut args, &arg)?),
+                "--max-degree" => builder.max_degree(next_count(&mut args, &arg)?),
+                "--families" => builder.families(
+                    next_value(&mut args, &arg)?
+                        .split(',')
+                        .map(|family| family.trim().parse())
+                        .collect::<Result<_, _>>()?,
+                ),
+                "--fit" => builder.only_family(Some(next_value(&mut args, &arg)?.parse()?)),
+                "--scaled-basis" => builder.scaled_basis(true),
+                "--zero-epsilon" => builder.zero_epsilon(next_number(
+                    &mut args,
+                    &arg,
+                    "a positive number",
+                    |value| value > 0.,
+                )?),
+                "--no-zero-fix-x" => builder.zero_fix_x(false),
+                "--no-zero-fix-y" => builder.zero_fix_y(false),
+                "--ambiguity-threshold" => builder.ambiguity_threshold(next_number(
+                    &mut args,
+                    &arg,
+                    "a non-negative fraction",
+                    |value| value >= 0.,
+                )?),
+                "--criterion" => builder.criterion(next_value(&mut args, &arg)?.parse()?),
+                "--plot-output" => builder.plot_path(next_value(&mut args, &arg)?.into()),
+                "--plot-format" => builder.plot_format(next_value(&mut args, &arg)?.parse()?),
+                "--plot-size" => builder.plot_size(next_value(&mut args, &arg)?.parse()?),
+                "--residual-plot" => {
+                    builder.residual_plot(Some(next_value(&mut args, &arg)?.into()))
+                }
+                "--point-size" => builder.point_size(next_count(&mut args, &arg)?),
+                "--curve-color" => builder.curve_color(Some(next_value(&mut args, &arg)?.parse()?)),
+                "--curve-width" => builder.curve_width(next_count(&mut args, &arg)?),
+                "--plot-margin" => builder.coord_margin(next_number(
+                    &mut args,
+                    &arg,
+                    "a fraction from 0 to 1",
+                    |value| (0. ..=plot::MAX_COORD_MARGIN).contains(&value),
+                )?),
+                "--plot-max-points" => {
+                    let max = next_count(&mut args, &arg)?;
+                    if max == 0 {
+                        return Err("--plot-max-points must be positive".into());
+                    }
+                    builder.max_points(Some(max))
+                }
+                "--font" => builder.font(next_value(&mut args, &arg)?),
+                "--font-size" => {
+                    let size = next_count(&mut args, &arg)?;
+                    if size == 0 {
+                        return Err("--font-size must be positive".into());
+                    }
+                    builder.font_size(size)
+                }
+                "--log-x" => builder.log_x(true),
+                "--log-y" => builder.log_y(true),
+                "--no-plot" => builder.no_plot(true),
+                "--timing" => builder.timing(true),
+                "--list-families" => builder.list_families(true),
+                "--condition-number" => builder.condition_number(true),
+                "--debug-matrix" => builder.debug_matrix(true),
+                "--seed-points" => builder.seed_points(Some(next_count(&mut args, &arg)?)),
+                "--predict" => builder.predict(next_numbers(&mut args, &arg)?),
+                "--slope-at" => builder.slope_at(next_numbers(&mut args, &arg)?),
+                "--integrate" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let error = || format!("{} expects two numbers `a,b`, got `{}`", arg, value);
+                    let (start, end) = value.split_once(',').ok_or_else(error)?;
+                    let start: TNumber = start.trim().parse().map_err(|_| error())?;
+                    let end: TNumber = end.trim().parse().map_err(|_| error())?;
+                    if !(start.is_finite() && end.is_finite()) {
+                        return Err(error().into());
+                    }
+                    builder.integrate(Some(start..end))
+                }
+                "--relative" => builder.relative(true),
+                "--sci" => builder.scientific(true),
+                "--ascii" => builder.ascii(true),
+                "--sort-x" => builder.sort_x(true),
+                "--swap-axes" => builder.swap_axes(true),
+                "--top-errors" => builder.top_errors(next_count(&mut args, &arg)?),
+                "--smooth-window" => builder.smooth_window(Some(next_count(&mut args, &arg)?)),
+                "--smooth-degree" => builder.smooth_degree(next_count(&mut args, &arg)?),
+                "--compare" => builder.compare(Some(next_value(&mut args, &arg)?.into())),
+                "--dump-fit" => builder.dump_fit(Some(next_value(&mut args, &arg)?.into())),
+                _ if arg.starts_with("--") => {
+                    return Err(format!("unknown argument `{}`", arg).into())
+                }
+                _ => builder.path(PathBuf::from(arg)),
+            };
+        }
+
+        builder.build()
+    }
+}
+
+/// Builds a [`RunConfig`] field by field, e.g.
+/// `RunConfig::builder().criterion(Criterion::Aic).no_plot(true).build()`.
+///
+/// Setters of [`FitOptions`], [`PlotOptions`] and [`CsvOptions`] fields go straight
+/// to that part, [`RunConfigBuilder::build`] checks the options fit together
+#[derive(Debug, Default)]
+pub struct RunConfigBuilder {
+    config: RunConfig,
+    /// Default path of the plot format otherwise
+    plot_path: Option<PathBuf>,
+}
+
+/// `name => path.to.field: Type` becomes `fn name(self, value: Type) -> Self`
+macro_rules! setters {
+    ($($name:ident => $($field:ident).+: $type:ty,)*) => {
+        impl RunConfigBuilder {
+            $(
+                pub fn $name(mut self, value: $type) -> Self {
+                    self.config.$($field).+ = value;
+                    self
+                }
+            )*
+        }
+    };
+}
+
+setters! {
+    format => format: InputFormat,
+    output => output: OutputFormat,
+    delimiter => csv.delimiter: Option<char>,
+    decimal_comma => csv.decimal_comma: bool,
+    precision => precision: usize,
+    max_degree => fit.max_degree: usize,
+    families => fit.families: Vec<Family>,
+    scaled_basis => fit.scaled_basis: bool,
+    zero_epsilon => fit.zero_epsilon: TNumber,
+    zero_fix_x => fit.zero_fix_x: bool,
+    zero_fix_y => fit.zero_fix_y: bool,
+    plot_format => plot.format: PlotFormat,
+    plot_size => plot.size: PlotSize,
+    point_size => plot.point_size: u32,
+    curve_color => plot.curve_color: Option<PlotColor>,
+    curve_width => plot.curve_width: u32,
+    coord_margin => plot.coord_margin: TNumber,
+    max_points => plot.max_points: Option<usize>,
+    font => plot.font: String,
+    font_size => plot.font_size: u32,
+    log_x => plot.log_x: bool,
+    log_y => plot.log_y: bool,
+    no_plot => no_plot: bool,
+    ambiguity_threshold => ambiguity_threshold: TNumber,
+    criterion => criterion: Criterion,
+    timing => timing: bool,
+    dump_fit => dump_fit: Option<PathBuf>,
+    predict => predict: Vec<TNumber>,
+    slope_at => slope_at: Vec<TNumber>,
+    integrate => integrate: Option<Range<TNumber>>,
+    debug_matrix => debug_matrix: bool,
+    seed_points => seed_points: Option<usize>,
+    residual_plot => residual_plot: Option<PathBuf>,
+    top_errors => top_errors: usize,
+    only_family => only_family: Option<Family>,
+    sort_x => sort_x: bool,
+    scientific => scientific: bool,
+    relative => relative: bool,
+    condition_number => condition_number: bool,
+    smooth_window => smooth_window: Option<usize>,
+    smooth_degree => smooth_degree: usize,
+    ascii => ascii: bool,
+    swap_axes => swap_axes: bool,
+    compare => compare: Option<PathBuf>,
+    list_families => list_families: bool,
+}
+
+impl RunConfigBuilder {
+    /// Adds an input file, see [`RunConfig::paths`]
+    pub fn path(mut self, path: PathBuf) -> Self {
+        self.config.paths.push(path);
+        self
+    }
+
+    /// Where the plot is saved, [`PlotFormat::default_path`] when not set
+    pub fn plot_path(mut self, path: PathBuf) -> Self {
+        self.plot_path = Some(path);
+        self
+    }
+
+    /// Fails on options that contradict each other, e.g. `--compare` with several inputs
+    pub fn build(self) -> Result<RunConfig, Box<dyn Error>> {
+        let RunConfigBuilder {
+            mut config,
+            plot_path,
+        } = self;
+
+        if let Some(family) = config.only_family {
+            if let Some(degrees) = family
+                .degrees(config.fit.max_degree)
+                .filter(|degrees| degrees.is_empty())
+            {
+                return Err(format!(
+                    "--fit {} has no degree within --max-degree {}, raise it to at least {}",
+                    family.key(),
+                    config.fit.max_degree,
+                    degrees.start()
+                )
+                .into());
+            }
+            config.fit.families = vec![family];
+        }
+
+        config.plot.path = plot_path.unwrap_or_else(|| config.plot.format.default_path());
+
+        if config.csv.decimal_comma && config.csv.delimiter.unwrap_or(',') == ',' {
+            return Err("--decimal-comma requires a --delimiter other than `,`".into());
+        }
+
+        if config.seed_points.is_some() && !config.paths.is_empty() {
+            return Err("--seed-points can not be combined with an input file".into());
+        }
+
+        if config.compare.is_some() {
+            if config.paths.len() > 1 {
+                return Err("--compare takes a single input file to compare with".into());
+            }
+            if config.seed_points.is_some() || config.smooth_window.is_some() {
+                return Err(
+                    "--compare can not be combined with --seed-points or --smooth-window".into(),
+                );
+            }
+            if let OutputFormat::Json | OutputFormat::Csv = config.output {
+                return Err("--compare supports only table and quiet output".into());
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{} requires a value", flag))
+}
+
+fn next_numbers(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<Vec<TNumber>, String> {
+    let value = next_value(args, flag)?;
+    value
+        .split(',')
+        .map(|x| x.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("{} expects comma separated numbers, got `{}`", flag, value))
+}
+
+fn next_count<T: FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, String> {
+    let value = next_value(args, flag)?;
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a non-negative integer, got `{}`", flag, value))
+}
+
+fn next_number(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+    expected: &str,
+    is_valid: fn(TNumber) -> bool,
+) -> Result<TNumber, String> {
+    let value = next_value(args, flag)?;
+    value
+        .parse()
+        .ok()
+        .filter(|&number| is_valid(number))
+        .ok_or_else(|| format!("{} expects {}, got `{}`", flag, expected, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_matches_parsed_flags() {
+        let built = RunConfig::builder()
+            .criterion(Criterion::Aic)
+            .max_degree(5)
+            .plot_format(PlotFormat::Svg)
+            .path("points.json".into())
+            .build()
+            .unwrap();
+        let parsed = RunConfig::parse(
+            [
+                "--criterion",
+                "aic",
+                "--max-degree",
+                "5",
+                "--plot-format",
+                "svg",
+                "points.json",
+            ]
+            .into_iter()
+            .map(str::to_owned),
+        )
+        .unwrap();
+
+        assert_eq!(format!("{:?}", built), format!("{:?}", parsed));
+        assert_eq!(built.plot.path, PlotFormat::Svg.default_path());
+    }
+
+    #[test]
+    fn builder_rejects_decimal_comma_without_delimiter() {
+        let error = RunConfig::builder()
+            .decimal_comma(true)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "--decimal-comma requires a --delimiter other than `,`"
+        );
+    }
+}
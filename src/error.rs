@@ -0,0 +1,57 @@
+use std::{fmt::Display, io, path::PathBuf};
+
+/// Why a run failed, each kind exits with its own code so scripts can tell them apart
+#[derive(Debug)]
+pub enum RunError {
+    /// Arguments or points that can not be fitted at all
+    BadInput(String),
+    /// Points were fine, but no family produced a usable approximation
+    NoValidFit(String),
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+}
+
+impl RunError {
+    /// Code 1 is left for other failures, e.g. a plot that could not be drawn
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunError::BadInput(_) => 2,
+            RunError::NoValidFit(_) => 3,
+            RunError::Io { .. } => 4,
+        }
+    }
+
+    /// Same kind of error with `context: ` in front of the message
+    pub fn within(self, context: &str) -> RunError {
+        match self {
+            RunError::BadInput(message) => RunError::BadInput(format!("{}: {}", context, message)),
+            RunError::NoValidFit(message) => {
+                RunError::NoValidFit(format!("{}: {}", context, message))
+            }
+            // the path already tells where it happened
+            error @ RunError::Io { .. } => error,
+        }
+    }
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::BadInput(message) | RunError::NoValidFit(message) => write!(f, "{}", message),
+            RunError::Io { path, source } => {
+                write!(f, "cannot read `{}`: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    error::Error,
+    io::{BufRead, Read},
+};
+
+use comp_math_lab4::{Point, TNumber};
+use serde::Deserialize;
+
+const JSON_SCHEMA: &str = r#"a JSON array of {"x": .., "y": .., "w": optional weight} or an object holding it under "points""#;
+
+/// Input points with whatever describes them
+#[derive(Debug, Default)]
+pub struct Dataset {
+    pub name: Option<String>,
+    pub points: Vec<Point>,
+}
+
+impl From<Vec<Point>> for Dataset {
+    fn from(points: Vec<Point>) -> Self {
+        Dataset { name: None, points }
+    }
+}
+
+/// Object layout of the JSON input, other fields (e.g. units) are ignored
+#[derive(Deserialize)]
+struct JsonDataset {
+    name: Option<String>,
+    points: Vec<Point>,
+}
+
+/// Reads a JSON array of points or an object with `points` and optional `name`,
+/// errors mention the expected schema
+pub fn read_json(mut reader: impl Read) -> Result<Dataset, Box<dyn Error>> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+
+    if input.trim().is_empty() {
+        return Err(format!("no input points provided (expected {})", JSON_SCHEMA).into());
+    }
+
+    // the layout is picked up front, so serde still tells the line and column of an error
+    let invalid =
+        |error: serde_json::Error| format!("invalid input: {} (expected {})", error, JSON_SCHEMA);
+    match input.trim_start().as_bytes()[0] {
+        b'[' => Ok(serde_json::from_str::<Vec<Point>>(&input)
+            .map_err(invalid)?
+            .into()),
+        b'{' => {
+            let JsonDataset { name, points } = serde_json::from_str(&input).map_err(invalid)?;
+            Ok(Dataset { name, points })
+        }
+        _ => Err(format!("invalid input: expected {}", JSON_SCHEMA).into()),
+    }
+}
+
+/// Reads one `{"x": .., "y": ..}` object per line, blank lines are skipped.
+///
+/// Unlike a single array a truncated stream still yields every complete line before it
+pub fn read_ndjson(reader: impl BufRead) -> Result<Vec<Point>, Box<dyn Error>> {
+    let mut points = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let point = serde_json::from_str(&line).map_err(|error| {
+            format!(
+                r#"line {}: invalid point: {} (expected {{"x": .., "y": ..}} per line)"#,
+                index + 1,
+                error
+            )
+        })?;
+        points.push(point);
+    }
+
+    Ok(points)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CsvOptions {
+    /// Field separator, commas or whitespace when not set
+    pub delimiter: Option<char>,
+    /// Read `1,5` as `1.5`, needs a delimiter other than comma
+    pub decimal_comma: bool,
+}
+
+/// Parses `--delimiter` values, `tab` is accepted since it's hard to type
+pub fn parse_delimiter(value: &str) -> Result<char, String> {
+    match value {
+        "tab" | "\\t" => Ok('\t'),
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(delimiter), None) => Ok(delimiter),
+                _ => Err(format!(
+                    "invalid delimiter `{}`, expected a single character or `tab`",
+                    value
+                )),
+            }
+        }
+    }
+}
+
+/// Reads `x,y` rows with optional weight column separated by commas or whitespace,
+/// or by `options.delimiter` when set.
+///
+/// Blank lines and lines starting with `#` are skipped.
+/// The first remaining line is treated as a header when it holds no number at all,
+/// any other row that doesn't parse is an error naming its line.
+pub fn read_csv(reader: impl BufRead, options: CsvOptions) -> Result<Vec<Point>, Box<dyn Error>> {
+    let mut points = Vec::new();
+    let mut first_row = true;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        // header may come after leading comments
+        if !(first_row && is_header(&line, options)) {
+            let point = parse_row(&line, options)
+                .map_err(|error| format!("line {}: {}", line_number, error))?;
+            points.push(point);
+        }
+        first_row = false;
+    }
+
+    Ok(points)
+}
+
+/// No field is a number, even split on any usual separator,
+/// so `1;2` read with commas is a malformed row rather than a header
+fn is_header(line: &str, options: CsvOptions) -> bool {
+    !line
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace() || Some(c) == options.delimiter)
+        .any(|field| parse_number(field.trim(), options.decimal_comma).is_ok())
+}
+
+fn parse_row(line: &str, options: CsvOptions) -> Result<Point, String> {
+    let fields: Vec<_> = line
+        .split(|c: char| match options.delimiter {
+            Some(delimiter) => c == delimiter,
+            None => c == ',' || c.is_whitespace(),
+        })
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect();
+
+    let (x, y, w) = match fields[..] {
+        [x, y] => (x, y, None),
+        [x, y, w] => (x, y, Some(w)),
+        _ => {
+            return Err(format!(
+                "expected 2 or 3 values `x,y[,w]` where `w` is an optional weight, got {} in `{}`",
+                fields.len(),
+                line
+            ))
+        }
+    };
+
+    Ok(Point {
+        x: parse_number(x, options.decimal_comma)?,
+        y: parse_number(y, options.decimal_comma)?,
+        w: match w {
+            Some(w) => parse_number(w, options.decimal_comma)?,
+            None => 1.,
+        },
+    })
+}
+
+fn parse_number(field: &str, decimal_comma: bool) -> Result<TNumber, String> {
+    let normalized = if decimal_comma {
+        field.replace(',', ".")
+    } else {
+        field.to_owned()
+    };
+
+    normalized
+        .parse()
+        .map_err(|_| format!("`{}` is not a number", field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(points: &[Point]) -> Vec<(TNumber, TNumber)> {
+        points.iter().map(|point| (point.x, point.y)).collect()
+    }
+
+    #[test]
+    fn json_errors_keep_their_location() {
+        for input in [
+            r#"[{"x": 1, "y": "a"}]"#,
+            r#"{"points": [{"x": 1, "y": "a"}]}"#,
+        ] {
+            let error = read_json(input.as_bytes()).unwrap_err().to_string();
+            assert!(error.contains("line 1 column"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn reads_both_json_layouts() {
+        let points = read_json(r#"[{"x": 1, "y": 2}]"#.as_bytes()).unwrap();
+        assert_eq!(pairs(&points.points), [(1., 2.)]);
+
+        let dataset =
+            read_json(r#"{"name": "lab", "units": "m", "points": [{"x": 1, "y": 2}]}"#.as_bytes())
+                .unwrap();
+        assert_eq!(dataset.name.as_deref(), Some("lab"));
+        assert_eq!(pairs(&dataset.points), [(1., 2.)]);
+    }
+
+    #[test]
+    fn reads_decimal_commas_with_semicolons() {
+        let options = CsvOptions {
+            delimiter: Some(';'),
+            decimal_comma: true,
+        };
+        let points = read_csv("1,5;2,0\n2,5;3,25;0,5\n".as_bytes(), options).unwrap();
+        assert_eq!(pairs(&points), [(1.5, 2.0), (2.5, 3.25)]);
+        assert_eq!(points[0].w, 1.);
+        assert_eq!(points[1].w, 0.5);
+    }
+
+    #[test]
+    fn reads_tab_separated_rows() {
+        let options = CsvOptions {
+            delimiter: Some(parse_delimiter("tab").unwrap()),
+            decimal_comma: false,
+        };
+        let points = read_csv("1.5\t2.0\n3\t4\n".as_bytes(), options).unwrap();
+        assert_eq!(pairs(&points), [(1.5, 2.0), (3., 4.)]);
+
+        let points = read_csv("1.5\t2.0\n".as_bytes(), CsvOptions::default()).unwrap();
+        assert_eq!(pairs(&points), [(1.5, 2.0)]);
+    }
+
+    #[test]
+    fn rejects_malformed_numeric_first_row() {
+        for input in ["1;2\n2,3\n3,5\n", "1,2,1,7\n2,3\n3,5\n"] {
+            let error = read_csv(input.as_bytes(), CsvOptions::default()).unwrap_err();
+            assert!(error.to_string().starts_with("line 1: "), "{}", error);
+        }
+    }
+
+    #[test]
+    fn skips_interleaved_comments_and_blank_lines() {
+        let input = "1,2\n\n# measured again\n2,3\n   \n  # indented\n3,4\n";
+        let points = read_csv(input.as_bytes(), CsvOptions::default()).unwrap();
+        assert_eq!(pairs(&points), [(1., 2.), (2., 3.), (3., 4.)]);
+    }
+
+    #[test]
+    fn reads_header_after_leading_comments() {
+        let input = "# lab 4\n\n# units: s, m\nx,y\n1,2\n2,3\n";
+        let points = read_csv(input.as_bytes(), CsvOptions::default()).unwrap();
+        assert_eq!(pairs(&points), [(1., 2.), (2., 3.)]);
+    }
+
+    #[test]
+    fn rejects_malformed_row_after_leading_comments() {
+        let input = "# lab 4\n\n1;2\n2,3\n3,5\n";
+        let error = read_csv(input.as_bytes(), CsvOptions::default()).unwrap_err();
+        assert!(error.to_string().starts_with("line 3: "), "{}", error);
+    }
+
+    #[test]
+    fn rejects_text_after_the_first_row() {
+        let input = "# lab 4\n1,2\nx,y\n";
+        let error = read_csv(input.as_bytes(), CsvOptions::default()).unwrap_err();
+        assert!(error.to_string().starts_with("line 3: "), "{}", error);
+    }
+}
@@ -0,0 +1,135 @@
+use serde::Deserialize;
+
+pub mod error;
+pub mod methods;
+
+pub use error::RunError;
+pub use methods::{FitMetrics, Function, MinimizedFunction};
+
+pub type TNumber = f64;
+
+/// Fewest points [`best_fit`] and the command line tool accept,
+/// a single point makes every family degenerate
+pub const MIN_POINTS: usize = 2;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Point {
+    pub x: TNumber,
+    pub y: TNumber,
+    /// Weight of the point in least squares fitting
+    #[serde(default = "default_weight")]
+    pub w: TNumber,
+}
+
+fn default_weight() -> TNumber {
+    1.
+}
+
+impl Point {
+    /// Same point with x and y exchanged, the weight is kept
+    pub fn swapped(self) -> Point {
+        Point {
+            x: self.y,
+            y: self.x,
+            w: self.w,
+        }
+    }
+}
+
+/// Fits every known function family to the points.
+///
+/// Families that can not represent the points are skipped
+pub fn approximate(points: &[Point]) -> Vec<Box<dyn Function>> {
+    methods::create_approximations(points, &methods::FitOptions::default())
+}
+
+/// Fits every known function family and picks the one with the smallest
+/// standard deviation, the same way the command line tool does by default.
+/// Of families tied up to rounding the one with fewer parameters wins,
+/// see [`methods::rank`].
+///
+/// [`RunError::BadInput`] when there are fewer than [`MIN_POINTS`] points or all share one x,
+/// [`RunError::NoValidFit`] when no family has a finite standard deviation
+pub fn best_fit(points: &[Point]) -> Result<(Box<dyn Function>, FitMetrics), RunError> {
+    require_min_points(points)?;
+    methods::require_distinct_x(points).map_err(|error| RunError::BadInput(error.to_string()))?;
+
+    let mut functions = methods::create_approximations(points, &methods::FitOptions::default());
+    let mut metrics = FitMetrics::for_all(points, &functions);
+
+    let (ranking, _) = methods::rank(&functions, &metrics, methods::Criterion::default());
+    let best = *ranking.first().ok_or_else(|| {
+        RunError::NoValidFit(format!(
+            "no family fits the {} points with a finite standard deviation",
+            points.len()
+        ))
+    })?;
+    Ok((functions.swap_remove(best), metrics.swap_remove(best)))
+}
+
+/// [`RunError::BadInput`] naming how many points there are when fewer than [`MIN_POINTS`]
+pub fn require_min_points(points: &[Point]) -> Result<(), RunError> {
+    if points.len() < MIN_POINTS {
+        return Err(RunError::BadInput(format!(
+            "at least {} points required, got {}",
+            MIN_POINTS,
+            points.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Residuals, standard deviation and R² of any function, e.g. a custom [`Function`]
+/// implementation, measured the same way as the built-in families
+pub fn evaluate_fit(points: &[Point], function: &dyn Function) -> FitMetrics {
+    FitMetrics::new(points, function)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(pairs: &[(TNumber, TNumber)]) -> Vec<Point> {
+        pairs.iter().map(|&(x, y)| Point { x, y, w: 1. }).collect()
+    }
+
+    #[test]
+    fn best_fit_prefers_linear_on_a_line() {
+        let line: Vec<_> = (1..=8)
+            .map(|x| x as TNumber)
+            .map(|x| Point {
+                x,
+                y: 2. * x + 1.,
+                w: 1.,
+            })
+            .collect();
+        let (function, _) = best_fit(&line).unwrap();
+        assert_eq!(function.name(), "Linear");
+    }
+
+    #[test]
+    fn best_fit_prefers_quadratic_on_a_parabola() {
+        #[derive(Deserialize)]
+        struct Fixture {
+            points: Vec<Point>,
+        }
+        let fixture: Fixture =
+            serde_json::from_str(include_str!("../fixtures/quadratic.json")).unwrap();
+
+        let (function, _) = best_fit(&fixture.points).unwrap();
+        assert_eq!(function.name(), "Quadratic");
+    }
+
+    #[test]
+    fn best_fit_rejects_a_single_point() {
+        let error = best_fit(&points(&[(1., 2.)])).err().unwrap();
+        assert!(matches!(error, RunError::BadInput(_)), "{:?}", error);
+        assert_eq!(error.to_string(), "at least 2 points required, got 1");
+    }
+
+    #[test]
+    fn best_fit_draws_a_line_through_two_points() {
+        let (function, _) = best_fit(&points(&[(1., 2.), (2., 3.)])).unwrap();
+        assert_eq!(function.name(), "Linear");
+    }
+}
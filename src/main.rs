@@ -1,212 +1,828 @@
 use std::{
+    any::Any,
     error::Error,
-    ops::{Deref, Range},
+    fs::File,
+    io::{BufReader, Read},
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
-use cli_table::format::{Border, HorizontalLine, Separator, VerticalLine};
-use methods::Function;
-use serde::Deserialize;
-
-use crate::methods::create_approximations;
-
-mod methods;
-
-type TNumber = f64;
+use cli::{InputFormat, OutputFormat, RunConfig};
+use comp_math_lab4::{
+    methods::{
+        self, pearson_correlation, polynomial_family, require_distinct_x, smooth,
+        try_create_approximations, Coefficients, Criterion, Family, FitOptions, Linear, Polynomial,
+    },
+    require_min_points, FitMetrics, Function, Point, RunError, TNumber,
+};
+use input::Dataset;
+use plot::PlotOptions;
 
-#[derive(Clone, Copy, Debug, Deserialize)]
-struct Point {
-    pub x: TNumber,
-    pub y: TNumber,
-}
+mod cli;
+mod input;
+mod output;
+mod plot;
+mod synthetic;
 
 fn main() {
     match start() {
         Ok(_) => (),
-        Err(error) => eprintln!("{}", error),
+        Err(error) => {
+            eprintln!("{}", error);
+            let code = match error.downcast_ref::<BatchFailure>() {
+                Some(failure) => failure.exit_code,
+                None => error
+                    .downcast_ref::<RunError>()
+                    .map_or(1, RunError::exit_code),
+            };
+            std::process::exit(code);
+        }
+    }
+}
+
+/// Some of several datasets failed, each was already reported when it did
+#[derive(Debug)]
+struct BatchFailure {
+    failed: usize,
+    total: usize,
+    /// Shared [`RunError::exit_code`] of the failures, 1 when they differ
+    exit_code: i32,
+}
+
+impl std::fmt::Display for BatchFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} of {} datasets failed", self.failed, self.total)
     }
 }
 
-// ln(0) = -inf
-// so we need to account for point (0, y)
-const APPROX_ZERO: TNumber = 0.000001;
+impl Error for BatchFailure {}
 
 fn start() -> Result<(), Box<dyn Error>> {
-    let points: Vec<Point> = input_points()?
-        .iter()
-        .map(|&Point { x, y }| Point {
-            x: if x == 0. { APPROX_ZERO } else { x },
-            y: if y == 0. { APPROX_ZERO } else { y },
-        })
-        .collect();
+    let mut config = RunConfig::parse(std::env::args().skip(1))
+        .map_err(|error| RunError::BadInput(error.to_string()))?;
 
+    if config.list_families {
+        output::print_families();
+        return Ok(());
+    }
+
+    if (!config.no_plot || config.residual_plot.is_some())
+        && !plot::font_available(&config.plot.font)
+    {
+        let quiet = matches!(config.output, OutputFormat::Quiet);
+        // the fallback is a fontconfig lookup as well, it may fail just the same
+        if plot::font_available(plot::FALLBACK_FONT) {
+            if !quiet {
+                eprintln!(
+                    "Font `{}` is not available, using {} instead",
+                    config.plot.font,
+                    plot::FALLBACK_FONT
+                );
+            }
+            config.plot.font = plot::FALLBACK_FONT.to_owned();
+        } else {
+            if !quiet {
+                eprintln!(
+                    "Neither font `{}` nor {} is available, plots are drawn without text",
+                    config.plot.font,
+                    plot::FALLBACK_FONT
+                );
+            }
+            config.plot.labels = false;
+        }
+    }
+
+    if let Some(compare) = &config.compare {
+        let mut first = match config.paths.first() {
+            Some(path) => read_dataset(path, &config)?,
+            None => input_points(std::io::stdin(), &config)?,
+        };
+        first
+            .name
+            .get_or_insert_with(|| match config.paths.first() {
+                Some(path) => path.display().to_string(),
+                None => "Input".to_owned(),
+            });
+        let mut second = read_dataset(compare, &config)?;
+        second
+            .name
+            .get_or_insert_with(|| compare.display().to_string());
+        return compare_datasets([first, second], &config);
+    }
+
+    if let Some(count) = config.seed_points {
+        return fit_dataset(synthetic::generate(count).into(), &config, None);
+    }
+
+    match &config.paths[..] {
+        [] => fit_dataset(input_points(std::io::stdin(), &config)?, &config, None),
+        [path] => fit_dataset(read_dataset(path, &config)?, &config, None),
+        paths => {
+            // one bad file must not hide the results of the others
+            let mut exit_codes = Vec::new();
+            for (index, path) in paths.iter().enumerate() {
+                if index > 0 && !matches!(config.output, OutputFormat::Quiet) {
+                    println!();
+                }
+                let result = read_dataset(path, &config).and_then(|mut dataset| {
+                    dataset
+                        .name
+                        .get_or_insert_with(|| path.display().to_string());
+                    fit_dataset(dataset, &config, Some(index + 1))
+                });
+                if let Err(error) = result {
+                    let context = path.display().to_string();
+                    match error.downcast::<RunError>() {
+                        Ok(error) => {
+                            exit_codes.push(error.exit_code());
+                            eprintln!("{}", error.within(&context));
+                        }
+                        Err(error) => {
+                            exit_codes.push(1);
+                            eprintln!("{}: {}", context, error);
+                        }
+                    }
+                }
+            }
+
+            match exit_codes.first() {
+                None => Ok(()),
+                Some(&first) => Err(BatchFailure {
+                    failed: exit_codes.len(),
+                    total: paths.len(),
+                    exit_code: if exit_codes.iter().all(|&code| code == first) {
+                        first
+                    } else {
+                        1
+                    },
+                }
+                .into()),
+            }
+        }
+    }
+}
+
+fn read_dataset(path: &Path, config: &RunConfig) -> Result<Dataset, Box<dyn Error>> {
+    input_points(
+        File::open(path).map_err(|source| RunError::Io {
+            path: path.to_owned(),
+            source,
+        })?,
+        config,
+    )
+}
+
+/// `plot.png` becomes `plot_2.png`, so outputs of several datasets don't overwrite each other
+fn indexed_path(path: &Path, index: Option<usize>) -> PathBuf {
+    let Some(index) = index else {
+        return path.to_owned();
+    };
+
+    let mut name = path.file_stem().unwrap_or_default().to_owned();
+    name.push(format!("_{}", index));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// At least [`MIN_POINTS`](comp_math_lab4::MIN_POINTS) points with distinct x, swapped when x is fitted as a function of y
+fn prepare_points(mut points: Vec<Point>, config: &RunConfig) -> Result<Vec<Point>, RunError> {
+    require_min_points(&points)?;
+
+    if config.swap_axes {
+        points = points.into_iter().map(Point::swapped).collect();
+    }
+    require_distinct_x(&points).map_err(|error| RunError::BadInput(error.to_string()))?;
+    Ok(points)
+}
+
+/// Approximations and the metrics of each, in the same order
+type Fits = (Vec<Box<dyn Function>>, Vec<FitMetrics>);
+
+/// Every family that fitted with enough points left over, and its metrics
+fn fit_points(points: &[Point], config: &RunConfig) -> Result<Fits, Box<dyn Error>> {
     // compute minimal for each funciton
-    let all_approximations = create_approximations(&points);
+    let approximations = timed(config.timing, "Fitting", || {
+        try_create_approximations(points, &config.fit)
+    });
+    // there is nothing to fall back to when the family is forced
+    if config.only_family.is_some() {
+        if let Some(Err(error)) = approximations
+            .iter()
+            .find(|approximation| approximation.is_err())
+        {
+            return Err(RunError::NoValidFit(error.to_string()).into());
+        }
+    }
+    let quiet = matches!(config.output, OutputFormat::Quiet);
+    let approximations: Vec<_> = approximations
+        .into_iter()
+        .filter_map(|approximation| {
+            approximation
+                .map_err(|error| {
+                    if !quiet {
+                        eprintln!("Skipping: {}", error)
+                    }
+                })
+                .ok()
+        })
+        // more parameters than points are not determined by them, with as many
+        // the fit may still be exact, see `methods::rank`
+        .filter(|function| {
+            let parameters = function.coefficients().count();
+            let enough = points.len() >= parameters;
+            if !enough && !quiet {
+                eprintln!(
+                    "Skipping: {} has {} parameters, needs at least {} points, got {}",
+                    output::function_name(function.deref()),
+                    parameters,
+                    parameters,
+                    points.len()
+                );
+            }
+            enough
+        })
+        .collect();
 
     // compute total deviation for each
-    let approximated_points: Vec<_> = all_approximations
+    let metrics = timed(config.timing, "Deviation", || {
+        FitMetrics::for_all(points, &approximations)
+    });
+    Ok((approximations, metrics))
+}
+
+/// [`methods::rank`] by `--criterion`, only the highest degree when the family is forced.
+///
+/// Fails when nothing may win
+fn rank(
+    approximations: &[Box<dyn Function>],
+    metrics: &[FitMetrics],
+    config: &RunConfig,
+) -> Result<(Vec<usize>, Vec<TNumber>), RunError> {
+    let (mut ranking, scores) = methods::rank(approximations, metrics, config.criterion);
+    // polynomial family yields every degree, the highest one is asked for
+    if config.only_family.is_some() {
+        ranking = approximations.len().checked_sub(1).into_iter().collect();
+    }
+    if ranking.is_empty() {
+        return Err(RunError::NoValidFit(match config.only_family {
+            Some(family) => format!("`{}` produced no approximation", family.key()),
+            None => "no approximation has a finite standard deviation".to_owned(),
+        }));
+    }
+    Ok((ranking, scores))
+}
+
+/// Fits, prints and plots a single dataset, `index` is set when there are several
+fn fit_dataset(
+    Dataset { name, points }: Dataset,
+    config: &RunConfig,
+    index: Option<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let points = prepare_points(points, config)?;
+
+    if let Some(window) = config.smooth_window {
+        let smoothed = smooth(&points, window, config.smooth_degree)?;
+        return output::print_smoothed(&points, &smoothed, config);
+    }
+
+    if config.debug_matrix {
+        print_normal_equations(&points, &config.fit, config.precision);
+    }
+    check_conditioning(
+        &points,
+        &config.fit,
+        config.condition_number,
+        config.precision,
+    );
+
+    let (all_approximations, metrics) = fit_points(&points, config)?;
+    let standard_deviations: Vec<_> = metrics
         .iter()
-        .map(|function| compute_deviation(&points, function.deref()))
+        .map(|metrics| metrics.standard_deviation)
         .collect();
-    let standard_deviations: Vec<f64> = approximated_points
-        .iter()
-        .map(|deviations| {
-            deviations
+
+    if let (OutputFormat::Table, Some(name)) = (config.output, &name) {
+        println!("Dataset: {}", name);
+    }
+    if let (OutputFormat::Table, true) = (config.output, config.swap_axes) {
+        println!("Axes are swapped, x is fitted as a function of y");
+    }
+
+    if let (OutputFormat::Table, None) = (config.output, config.only_family) {
+        output::print_summary(&all_approximations, &metrics, config)?;
+
+        if config.seed_points.is_some() {
+            print_recovered_line(&all_approximations, config.precision);
+        }
+    }
+
+    let (ranking, scores) = rank(&all_approximations, &metrics, config)?;
+    let best_index = ranking[0];
+    let quiet = matches!(config.output, OutputFormat::Quiet);
+    let best_function = all_approximations[best_index].deref();
+    let best_deviation = standard_deviations[best_index];
+    let deviations = &metrics[best_index].deviations;
+    // numbered by input position, the plots and JSON report keep the input order
+    let mut rows: Vec<_> = deviations.iter().enumerate().collect();
+    if config.sort_x {
+        rows.sort_by(|(_, a), (_, b)| a.0.x.total_cmp(&b.0.x));
+    }
+
+    match config.output {
+        OutputFormat::Table => {
+            if let Some(&runner_up_index) = ranking.get(1) {
+                let (best_score, runner_up_score) = (scores[best_index], scores[runner_up_index]);
+                if config.criterion.is_ambiguous(
+                    best_score,
+                    runner_up_score,
+                    config.ambiguity_threshold,
+                ) {
+                    println!(
+                        "Note: choice is ambiguous, {} ({:.*}) and {} ({:.*}) fit almost equally well",
+                        output::function_name(best_function),
+                        config.precision,
+                        best_score,
+                        output::function_name(all_approximations[runner_up_index].deref()),
+                        config.precision,
+                        runner_up_score,
+                    );
+                }
+            }
+            if config.scientific {
+                println!("{:#.*}", config.precision, best_function);
+            } else {
+                println!("{:.*}", config.precision, best_function);
+            }
+            if best_deviation.is_nan() {
+                // an exact fit with as many parameters as points
+                println!("Standard deviation is: — (no degrees of freedom left)");
+            } else {
+                println!(
+                    "Standard deviation is: {:.*}",
+                    config.precision, best_deviation
+                );
+            }
+            let (min_y, max_y) = points.iter().fold(
+                (TNumber::INFINITY, TNumber::NEG_INFINITY),
+                |(min, max), point| (min.min(point.y), max.max(point.y)),
+            );
+            // plain RMS over n, unlike the standard deviation, divided by the y range
+            // so it is dimensionless and comparable across datasets of different scale
+            if max_y > min_y {
+                let rmse = (deviations
+                    .iter()
+                    .map(|(_, _, epsilon)| epsilon.powi(2))
+                    .sum::<TNumber>()
+                    / deviations.len() as TNumber)
+                    .sqrt();
+                println!(
+                    "Normalized RMSE is: {:.*}",
+                    config.precision,
+                    rmse / (max_y - min_y)
+                );
+            } else {
+                println!("Normalized RMSE is: —");
+            }
+            if config.only_family.is_some() {
+                println!(
+                    "R² is: {:.*}",
+                    config.precision, metrics[best_index].r_squared
+                );
+            }
+            if let Criterion::Aic = config.criterion {
+                println!("AIC is: {:.*}", config.precision, scores[best_index]);
+            }
+            let (absolute_sum, max_error) = deviations
                 .iter()
-                .map(|(_, _, epsilon)| epsilon.powi(2))
-                .sum::<f64>()
-        })
-        .map(|epsilon_sum| (epsilon_sum / points.len() as f64).sqrt())
-        .collect();
+                .map(|(_, _, epsilon)| epsilon.abs())
+                .fold((0., 0. as TNumber), |(sum, max), error| {
+                    (sum + error, max.max(error))
+                });
+            println!(
+                "Mean absolute error is: {:.*}",
+                config.precision,
+                absolute_sum / deviations.len() as TNumber
+            );
+            println!("Max absolute error is: {:.*}", config.precision, max_error);
+            if let Some(linear) = (best_function as &dyn Any).downcast_ref::<Linear>() {
+                let Coefficients::Linear { a, b } = linear.coefficients() else {
+                    unreachable!("Linear has linear coefficients")
+                };
+                match linear.standard_errors(&points) {
+                    Some((a_error, b_error)) => {
+                        println!("a = {:.*} ± {:.*}", config.precision, a, config.precision, a_error);
+                        println!("b = {:.*} ± {:.*}", config.precision, b, config.precision, b_error);
+                    }
+                    None => println!(
+                        "Standard errors of a and b are unavailable, need more than 2 distinct points"
+                    ),
+                }
+
+                let r = pearson_correlation(&points);
+                if r.is_nan() {
+                    println!("Pearson correlation coefficient is: undefined");
+                } else {
+                    println!(
+                        "Pearson correlation coefficient is: {:.*}",
+                        config.precision, r
+                    );
+                }
+            }
+            output::print_points(&rows, config)?;
+            output::print_top_errors(deviations, config);
+            print_residual_signs(deviations);
+            if let Some(range) = &config.integrate {
+                println!(
+                    "Integral from {} to {} is: {:.*}",
+                    range.start,
+                    range.end,
+                    config.precision,
+                    best_function.integrate(range.clone())
+                );
+            }
+            for &x in &config.slope_at {
+                println!(
+                    "Slope at {:.*} is: {:.*}",
+                    config.precision,
+                    x,
+                    config.precision,
+                    best_function.derivative(x)
+                );
+            }
+            for &x in &config.predict {
+                println!(
+                    "{:.*} -> {:.*}",
+                    config.precision,
+                    x,
+                    config.precision,
+                    best_function.compute(x)
+                );
+            }
+        }
+        OutputFormat::Csv => output::print_points_csv(&rows),
+        OutputFormat::Json => output::print_json(
+            &points,
+            best_function,
+            best_deviation,
+            deviations,
+            &config.predict,
+            config.number_style(),
+        )?,
+        OutputFormat::Quiet => output::print_coefficients(best_function, config.number_style())?,
+    }
+
+    if let Some(path) = &config.dump_fit {
+        output::write_gnuplot(&indexed_path(path, index), best_function, deviations)?;
+    }
 
-    let best_approximation = standard_deviations
+    let plot_options = PlotOptions {
+        path: indexed_path(&config.plot.path, index),
+        swap_axes: config.swap_axes,
+        progress: !quiet,
+        ..config.plot.clone()
+    };
+
+    if let Some(path) = &config.residual_plot {
+        let path = indexed_path(path, index);
+        plot::plot_residuals(deviations, &path, &plot_options)?;
+        if !quiet {
+            eprintln!("Residual plot saved at path: {}", path.display());
+        }
+    }
+
+    if config.no_plot {
+        return Ok(());
+    }
+
+    let labels: Vec<_> = all_approximations
         .iter()
-        .zip(all_approximations)
         .enumerate()
-        .min_by(move |(_, a), (_, b)| a.0.total_cmp(b.0))
-        .expect("At least one approximation present");
+        .map(|(index, function)| {
+            let name = output::function_name(function.deref());
+            if index == best_index {
+                format!("{} (best)", name)
+            } else {
+                name
+            }
+        })
+        .collect();
+
+    if !quiet {
+        eprintln!("Generating image. This may take several seconds");
+    }
+    timed(config.timing, "Plot", || {
+        plot::plot(
+            &points,
+            &all_approximations,
+            &labels,
+            best_index,
+            &plot_options,
+        )
+    })?;
+    if !quiet {
+        eprintln!("Image saved at path: {}", plot_options.path.display());
+    }
+    Ok(())
+}
 
-    println!("{}", best_approximation.1 .1);
-    println!("Standard deviation is: {:.5}", best_approximation.1 .0);
-    print_points(approximated_points.get(best_approximation.0).expect(
-        "amount of approximation arrays should match with number of approximation functions",
-    ))?;
+/// Fits every dataset, prints each winner and draws all of them on a single plot
+fn compare_datasets(datasets: [Dataset; 2], config: &RunConfig) -> Result<(), Box<dyn Error>> {
+    let mut fitted = Vec::new();
+    for (index, Dataset { name, points }) in datasets.into_iter().enumerate() {
+        let name = name.unwrap_or_default();
+        let points = prepare_points(points, config).map_err(|error| error.within(&name))?;
+        let (mut approximations, metrics) = fit_points(&points, config)?;
+        let (ranking, _) =
+            rank(&approximations, &metrics, config).map_err(|error| error.within(&name))?;
+        let best_index = ranking[0];
+        let best_function = approximations.swap_remove(best_index);
+        let best_metrics = &metrics[best_index];
 
-    plot(&points, best_approximation.1 .1.deref())
+        match config.output {
+            OutputFormat::Quiet => {
+                output::print_coefficients(best_function.deref(), config.number_style())?
+            }
+            _ => {
+                if index > 0 {
+                    println!();
+                }
+                println!("Dataset: {}", name);
+                if config.scientific {
+                    println!("{:#.*}", config.precision, best_function);
+                } else {
+                    println!("{:.*}", config.precision, best_function);
+                }
+                println!(
+                    "Standard deviation is: {:.*}",
+                    config.precision, best_metrics.standard_deviation
+                );
+                println!("R² is: {:.*}", config.precision, best_metrics.r_squared);
+            }
+        }
+
+        let label = format!("{}: {}", name, output::function_name(best_function.deref()));
+        fitted.push((points, vec![best_function], vec![label]));
+    }
+
+    if config.no_plot {
+        return Ok(());
+    }
+
+    let quiet = matches!(config.output, OutputFormat::Quiet);
+    let plot_options = PlotOptions {
+        swap_axes: config.swap_axes,
+        progress: !quiet,
+        ..config.plot.clone()
+    };
+    let series: Vec<_> = fitted
+        .iter()
+        // every curve is the best of its dataset, palette colors tell them apart
+        .map(|(points, functions, labels)| plot::Series {
+            points,
+            functions,
+            labels,
+            best: None,
+        })
+        .collect();
+    if !quiet {
+        eprintln!("Generating image. This may take several seconds");
+    }
+    timed(config.timing, "Plot", || {
+        plot::plot_comparison(&series, &plot_options)
+    })?;
+    if !quiet {
+        eprintln!("Image saved at path: {}", plot_options.path.display());
+    }
+    Ok(())
 }
 
-fn input_points() -> Result<Vec<Point>, serde_json::Error> {
-    serde_json::from_reader(std::io::stdin())
+/// Runs the stage and reports its duration to stderr, so stdout stays clean
+fn timed<T>(enabled: bool, stage: &str, action: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = action();
+    if enabled {
+        eprintln!(
+            "{} took {:.3} ms",
+            stage,
+            start.elapsed().as_secs_f64() * 1000.
+        );
+    }
+    result
 }
 
-fn compute_deviation(
-    points: &Vec<Point>,
-    function: &dyn Function,
-) -> Vec<(Point, TNumber, TNumber)> {
-    points
+fn input_points(reader: impl Read, config: &RunConfig) -> Result<Dataset, Box<dyn Error>> {
+    let dataset = match config.format {
+        InputFormat::Json => input::read_json(reader),
+        InputFormat::Csv => input::read_csv(BufReader::new(reader), config.csv).map(Dataset::from),
+        InputFormat::Ndjson => input::read_ndjson(BufReader::new(reader)).map(Dataset::from),
+    }
+    .map_err(|error| RunError::BadInput(error.to_string()))?;
+    let points = &dataset.points;
+
+    // NaN or infinity would silently poison every fit
+    if let Some(index) = points
         .iter()
-        .map(|&point| {
-            let phi = function.compute(point.x);
-            let epsilon = phi - point.y;
-            (point, phi, epsilon)
-        })
-        .collect()
+        .position(|point| !(point.x.is_finite() && point.y.is_finite() && point.w.is_finite()))
+    {
+        let point = points[index];
+        return Err(RunError::BadInput(format!(
+            "point {} is not finite: x = {}, y = {}, w = {}",
+            index + 1,
+            point.x,
+            point.y,
+            point.w
+        ))
+        .into());
+    }
+    // least squares would reward moving away from such a point
+    if let Some(index) = points.iter().position(|point| point.w < 0.) {
+        return Err(RunError::BadInput(format!(
+            "point {} has a negative weight w = {}",
+            index + 1,
+            points[index].w
+        ))
+        .into());
+    }
+
+    Ok(dataset)
 }
 
-fn print_points(points: &Vec<(Point, f64, f64)>) -> Result<(), Box<dyn std::error::Error>> {
-    use cli_table::Table;
-    let table = points
+/// Compares the generating line of `--seed-points` with the fitted one
+fn print_recovered_line(functions: &[Box<dyn Function>], precision: usize) {
+    println!(
+        "True line: {:.*}*x + {:.*}",
+        precision,
+        synthetic::TRUE_A,
+        precision,
+        synthetic::TRUE_B
+    );
+
+    let recovered = functions
         .iter()
-        .enumerate()
-        .map(|(index, point)| {
-            vec![
-                (index + 1).to_string(),
-                format!("{:.4}", point.0.x),
-                format!("{:.4}", point.0.y),
-                format!("{:.4}", point.1),
-                format!("{:.4}", point.2),
-            ]
-        })
-        .table()
-        .border(
-            Border::builder()
-                .top(HorizontalLine::new('╭', '╮', '┬', '─'))
-                .left(VerticalLine::new('│'))
-                .right(VerticalLine::new('│'))
-                .bottom(HorizontalLine::new('╰', '╯', '┴', '─'))
-                .build(),
-        )
-        .separator(
-            Separator::builder()
-                .row(Some(HorizontalLine::new('├', '┤', '┼', '─')))
-                .build(),
-        )
-        .title(["Point number", "X", "Y", "φ(x)", "ε"])
-        .display()?;
+        .find_map(|function| match function.coefficients() {
+            Coefficients::Linear { a, b } => Some((a, b)),
+            _ => None,
+        });
+    match recovered {
+        Some((a, b)) => println!(
+            "Recovered line: {:.*}*x + {:.*}",
+            precision, a, precision, b
+        ),
+        None => println!("Recovered line: linear fit not available"),
+    }
+}
 
-    println!("{table}");
-    return Ok(());
+/// Degrees of every polynomial family selected by the options
+fn polynomial_degrees(options: &FitOptions) -> Vec<usize> {
+    Family::ALL
+        .into_iter()
+        .filter(|family| options.families.contains(family))
+        .filter_map(|family| family.degrees(options.max_degree))
+        .flatten()
+        .collect()
 }
 
-fn with_coord_margin(range: Range<f64>, margin_persents: f64) -> Range<f64> {
-    let length = range.end - range.start;
-    let margin = length * margin_persents;
-    (range.start - margin)..(range.end + margin)
+/// Normal matrices with condition number above this give untrustworthy coefficients
+const ILL_CONDITIONED: TNumber = 1e10;
+
+/// Warns about ill-conditioned polynomial fits, `print` also reports every
+/// condition number. Both go to stderr
+fn check_conditioning(points: &[Point], options: &FitOptions, print: bool, precision: usize) {
+    // families with too few points are skipped anyway
+    let degrees = polynomial_degrees(options)
+        .into_iter()
+        .filter(|&degree| points.len() > degree + 1);
+    for degree in degrees {
+        let condition = Polynomial::condition_number(points, degree, options.scaled_basis);
+        if print {
+            eprintln!(
+                "Condition number of {}: {:.*e}",
+                polynomial_family(degree),
+                precision,
+                condition
+            );
+        }
+        if condition > ILL_CONDITIONED {
+            eprintln!(
+                "Warning: {} normal equations are ill-conditioned ({:.*e}), the fit may be wrong{}",
+                polynomial_family(degree),
+                precision,
+                condition,
+                if options.scaled_basis {
+                    ""
+                } else {
+                    ", try --scaled-basis"
+                }
+            );
+        }
+    }
 }
 
-fn plot(points: &Vec<Point>, function: &dyn Function) -> Result<(), Box<dyn std::error::Error>> {
-    use plotters::prelude::*;
-    const MARGINS: i32 = 10;
-    const COORD_MARGIN_PERSENT: TNumber = 0.05;
-    const IMAGE_PATH: &'static str = "./plot.png";
-    const POINT_SIZE: i32 = 10;
+/// Systems solved by the polynomial families, printed to stderr
+fn print_normal_equations(points: &[Point], options: &FitOptions, precision: usize) {
+    for degree in polynomial_degrees(options) {
+        let (matrix, rhs) = Polynomial::normal_equations(points, degree);
+        eprintln!("Normal equations of {}:", polynomial_family(degree));
+        for (row, value) in matrix.iter().zip(rhs) {
+            let row: Vec<_> = row
+                .iter()
+                .map(|entry| format!("{:.*e}", precision, entry))
+                .collect();
+            eprintln!("  {} | {:.*e}", row.join(" "), precision, value);
+        }
+    }
+}
 
-    println!("Generating image. This may take several seconds");
+/// Long runs of same sign residuals hint that the model is wrong even if deviation is small
+fn print_residual_signs(deviations: &[(Point, TNumber, TNumber)]) {
+    let mut sorted = deviations.to_vec();
+    sorted.sort_by(|a, b| a.0.x.total_cmp(&b.0.x));
 
-    let x_range = {
-        let min = points
-            .iter()
-            .min_by(|a, b| a.x.total_cmp(&b.x))
-            .expect("At least one point present");
-        let max = points
-            .iter()
-            .max_by(|a, b| a.x.total_cmp(&b.x))
-            .expect("At least one point present");
+    let positive = sorted
+        .iter()
+        .filter(|(_, _, epsilon)| *epsilon > 0.)
+        .count();
+    let negative = sorted
+        .iter()
+        .filter(|(_, _, epsilon)| *epsilon < 0.)
+        .count();
+    let sign_changes = sorted
+        .iter()
+        .map(|(_, _, epsilon)| *epsilon)
+        .filter(|epsilon| *epsilon != 0.)
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|pair| pair[0].signum() != pair[1].signum())
+        .count();
 
-        min.x..max.x
-    };
+    println!("Residuals: {} positive, {} negative", positive, negative);
+    println!("Sign changes along x: {}", sign_changes);
+}
 
-    let y_range = {
-        let min = points
-            .iter()
-            .min_by(|a, b| a.y.total_cmp(&b.y))
-            .expect("At least one point present");
-        let max = points
-            .iter()
-            .max_by(|a, b| a.y.total_cmp(&b.y))
-            .expect("At least one point present");
+#[cfg(test)]
+mod tests {
+    use comp_math_lab4::methods::AnyFunction;
 
-        min.y..max.y
-    };
+    use super::*;
 
-    let root = BitMapBackend::new(IMAGE_PATH, (1920, 1080)).into_drawing_area();
-    root.fill(&WHITE)?;
-    let root = root.margin(MARGINS, MARGINS, MARGINS, MARGINS);
-
-    let mut chart = ChartBuilder::on(&root)
-        .margin(MARGINS * 2)
-        .x_label_area_size(20)
-        .y_label_area_size(40)
-        .build_cartesian_2d(
-            with_coord_margin(x_range.clone(), COORD_MARGIN_PERSENT),
-            with_coord_margin(y_range, COORD_MARGIN_PERSENT),
-        )?;
-
-    chart
-        .configure_mesh()
-        .label_style(("noto sans", 16))
-        .x_labels(5)
-        .y_labels(5)
-        .x_desc("X")
-        .y_desc("Y")
-        .draw()?;
-    chart.draw_series(PointSeries::<_, _, Circle<_, _>, _>::new(
-        points.iter().map(|point| (point.x, point.y)),
-        POINT_SIZE,
-        BLACK.filled(),
-    ))?;
-
-    chart.draw_series(LineSeries::new(
-        x_range
-            .clone()
-            .step(0.05)
-            .values()
-            .chain([x_range.end])
-            .map(|x| (x, function.compute(x))),
-        GREEN.stroke_width(3),
-    ))?;
-
-    root.present()?;
-
-    println!("Image saved at path: {}", IMAGE_PATH);
-    Ok(())
+    fn function(coefficients: Coefficients) -> Box<dyn Function> {
+        Box::new(AnyFunction::try_from(coefficients).unwrap())
+    }
+
+    fn line() -> Vec<Point> {
+        // alternating noise keeps the deviation away from zero
+        (1..=6)
+            .map(|x| (x as TNumber, if x % 2 == 0 { 0.1 } else { -0.1 }))
+            .map(|(x, noise)| Point {
+                x,
+                y: 2. * x + 1. + noise,
+                w: 1.,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finite_family_wins_over_overflowing_exponent() {
+        let points = line();
+        // overflows at every point, so its deviation is not finite
+        let approximations = vec![
+            function(Coefficients::Exponent {
+                a0: 1000.,
+                a1: 1000.,
+            }),
+            function(Coefficients::Linear { a: 2., b: 1. }),
+        ];
+        let metrics = FitMetrics::for_all(&points, &approximations);
+        assert!(!metrics[0].standard_deviation.is_finite());
+
+        for criterion in [Criterion::Rms, Criterion::Aic] {
+            let config = RunConfig::builder().criterion(criterion).build().unwrap();
+            let (ranking, _) = rank(&approximations, &metrics, &config).unwrap();
+            assert_eq!(ranking, [1]);
+        }
+    }
+
+    #[test]
+    fn negative_weight_is_rejected_with_its_index() {
+        let input = r#"[{"x": 1, "y": 2}, {"x": 2, "y": 3, "w": 0}, {"x": 3, "y": 4, "w": -1}]"#;
+        let error = input_points(input.as_bytes(), &RunConfig::default()).unwrap_err();
+        assert_eq!(error.to_string(), "point 3 has a negative weight w = -1");
+    }
+
+    #[test]
+    fn rank_fails_when_no_deviation_is_finite() {
+        let points = line();
+        let approximations = vec![function(Coefficients::Exponent {
+            a0: 1000.,
+            a1: 1000.,
+        })];
+        let metrics = FitMetrics::for_all(&points, &approximations);
+
+        let error = rank(&approximations, &metrics, &RunConfig::default()).unwrap_err();
+        assert!(matches!(error, RunError::NoValidFit(_)), "{:?}", error);
+    }
 }
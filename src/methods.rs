@@ -1,273 +1,3291 @@
-use std::fmt::Display;
+use std::{
+    any::Any,
+    fmt::Display,
+    ops::{Range, RangeInclusive},
+    str::FromStr,
+};
 
 use mathru::algebra::linear::{
-    matrix::{General, Solve},
+    matrix::{General, Inverse, Solve},
     vector::Vector,
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{Point, TNumber};
 
-pub trait Function: Display {
+/// Sums of squares below this are treated as zero
+const SUM_OF_SQUARES_TOLERANCE: TNumber = 1e-12;
+
+/// `Send + Sync` lets fits run on several threads, see the `parallel` feature
+pub trait Function: Display + Any + Send + Sync {
+    /// Family name without the formula, e.g. `Linear`
+    fn name(&self) -> &'static str;
+
     fn compute(&self, x: TNumber) -> TNumber;
+
+    /// Fitted coefficients, use [`Coefficients::values`] for plain numbers
+    fn coefficients(&self) -> Coefficients;
+
+    /// Equation on a single line with coefficients formatted in the style,
+    /// `Display` prints the family name line and then this
+    fn formula(&self, style: NumberStyle) -> String;
+
+    /// Boxed copy, makes `Box<dyn Function>` cloneable
+    fn clone_box(&self) -> Box<dyn Function>;
+
+    /// Evaluates the function at `steps + 1` evenly spaced x,
+    /// including both ends of the range
+    fn sample(&self, range: Range<TNumber>, steps: usize) -> Vec<(TNumber, TNumber)> {
+        if steps == 0 {
+            return vec![(range.start, self.compute(range.start))];
+        }
+
+        let step = (range.end - range.start) / steps as TNumber;
+        (0..=steps)
+            .map(|i| {
+                // exact end instead of accumulated rounding error
+                let x = if i == steps {
+                    range.end
+                } else {
+                    range.start + step * i as TNumber
+                };
+                (x, self.compute(x))
+            })
+            .collect()
+    }
+
+    /// Slope at `x`, central difference unless the family has a closed form
+    fn derivative(&self, x: TNumber) -> TNumber {
+        // relative step keeps the difference above rounding noise for large x
+        let step = DERIVATIVE_STEP * x.abs().max(1.);
+        (self.compute(x + step) - self.compute(x - step)) / (2. * step)
+    }
+
+    /// Definite integral from `range.start` to `range.end`, negative for a reversed range.
+    ///
+    /// Trapezoidal rule over [`Function::sample`] unless the family has a closed form
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        self.sample(range, INTEGRATION_STEPS)
+            .windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0) * (pair[0].1 + pair[1].1) / 2.)
+            .sum()
+    }
+
+    /// Coefficient of determination: `1 - SS_res / SS_tot`.
+    ///
+    /// When all y are identical (`SS_tot = 0`) returns `1.0` for exact fit
+    /// and `NAN` otherwise.
+    fn r_squared(&self, points: &[Point]) -> TNumber {
+        let mean = points.iter().map(|point| point.y).sum::<TNumber>() / points.len() as TNumber;
+        let (ss_res, ss_tot) =
+            points
+                .iter()
+                .fold((0., 0.), |(ss_res, ss_tot), &Point { x, y, .. }| {
+                    (
+                        ss_res + (y - self.compute(x)).powi(2),
+                        ss_tot + (y - mean).powi(2),
+                    )
+                });
+
+        if ss_tot < SUM_OF_SQUARES_TOLERANCE {
+            return if ss_res < SUM_OF_SQUARES_TOLERANCE {
+                1.
+            } else {
+                TNumber::NAN
+            };
+        }
+
+        1. - ss_res / ss_tot
+    }
 }
 
-pub trait MinimizedFunction {
-    fn new_minimized(points: &Vec<Point>) -> Self;
+/// Trapezoids used by the default [`Function::integrate`]
+const INTEGRATION_STEPS: usize = 10_000;
+
+/// Relative step of the default [`Function::derivative`]
+const DERIVATIVE_STEP: TNumber = 1e-6;
+
+/// Derivative of `coeffs[0] + coeffs[1]*x + ... + coeffs[n]*x^n` at `x`
+fn polynomial_derivative(coeffs: &[TNumber], x: TNumber) -> TNumber {
+    coeffs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .rev()
+        .fold(0., |sum, (power, coeff)| sum * x + coeff * power as TNumber)
 }
 
-pub fn create_approximations(points: &Vec<Point>) -> Vec<Box<dyn Function>> {
-    vec![
-        Box::new(Linear::new_minimized(points)),
-        Box::new(Quadratic::new_minimized(points)),
-        Box::new(Cubic::new_minimized(points)),
-        Box::new(Exponent::new_minimized(points)),
-        Box::new(Logrithm::new_minimized(points)),
-        Box::new(Power::new_minimized(points)),
-    ]
+/// Integral of `coeffs[0] + coeffs[1]*x + ... + coeffs[n]*x^n` over the range
+fn polynomial_integral(coeffs: &[TNumber], range: Range<TNumber>) -> TNumber {
+    let antiderivative = |x: TNumber| {
+        coeffs
+            .iter()
+            .enumerate()
+            .rev()
+            .fold(0., |sum, (power, coeff)| {
+                sum * x + coeff / (power + 1) as TNumber
+            })
+            * x
+    };
+    antiderivative(range.end) - antiderivative(range.start)
 }
 
-pub struct Linear {
-    /// Multiplier
-    a: TNumber,
-    /// Addition
-    b: TNumber,
+/// Fitted coefficients of each function family,
+/// enough to reconstruct the function
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+pub enum Coefficients {
+    /// `a*x + b`
+    Linear { a: TNumber, b: TNumber },
+    /// `a*x`
+    LinearNoIntercept { a: TNumber },
+    /// `a0 + a1*x + a2*x^2`
+    Quadratic {
+        a0: TNumber,
+        a1: TNumber,
+        a2: TNumber,
+    },
+    /// `a0 + a1*x + a2*x^2 + a3*x^3`
+    Cubic {
+        a0: TNumber,
+        a1: TNumber,
+        a2: TNumber,
+        a3: TNumber,
+    },
+    /// `e^(a0*x + a1)`
+    Exponent { a0: TNumber, a1: TNumber },
+    /// `a0*ln(x) + a1`
+    Logarithm { a0: TNumber, a1: TNumber },
+    /// `a0*log10(x) + a1`
+    Log10 { a0: TNumber, a1: TNumber },
+    /// `e^a0 * x^a1`
+    Power { a0: TNumber, a1: TNumber },
+    /// `amplitude*sin(frequency*x + phase) + offset`
+    Sinusoidal {
+        amplitude: TNumber,
+        frequency: TNumber,
+        phase: TNumber,
+        offset: TNumber,
+    },
+    /// `amplitude*atan(scale*x + shift) + offset`
+    ArcTan {
+        scale: TNumber,
+        shift: TNumber,
+        amplitude: TNumber,
+        offset: TNumber,
+    },
+    /// `coeffs[0] + coeffs[1]*x + ... + coeffs[n]*x^n`
+    Polynomial { coeffs: Vec<TNumber> },
+    /// `a/x + b`
+    Hyperbolic { a: TNumber, b: TNumber },
+    /// `a*sqrt(x) + b`
+    SquareRoot { a: TNumber, b: TNumber },
+    /// `a*x + b` fitted with Theil–Sen estimator
+    RobustLinear { a: TNumber, b: TNumber },
+    /// `amplitude*e^(-(x - mean)^2 / (2*sigma^2))`
+    Gaussian {
+        amplitude: TNumber,
+        mean: TNumber,
+        sigma: TNumber,
+    },
+    /// Straight segments between `knots` sorted by x
+    PiecewiseLinear { knots: Vec<(TNumber, TNumber)> },
 }
 
-// special thanks to Lannee for implementation of all minimization rutines
+impl Coefficients {
+    /// Coefficients in the order of the variant fields,
+    /// e.g. `[a, b]` for linear and `[a0, a1, a2]` for quadratic
+    pub fn values(&self) -> Vec<TNumber> {
+        match self {
+            Coefficients::Linear { a, b }
+            | Coefficients::Hyperbolic { a, b }
+            | Coefficients::SquareRoot { a, b }
+            | Coefficients::RobustLinear { a, b } => vec![*a, *b],
+            Coefficients::LinearNoIntercept { a } => vec![*a],
+            Coefficients::Quadratic { a0, a1, a2 } => vec![*a0, *a1, *a2],
+            Coefficients::Cubic { a0, a1, a2, a3 } => vec![*a0, *a1, *a2, *a3],
+            Coefficients::Exponent { a0, a1 }
+            | Coefficients::Logarithm { a0, a1 }
+            | Coefficients::Log10 { a0, a1 }
+            | Coefficients::Power { a0, a1 } => vec![*a0, *a1],
+            Coefficients::Sinusoidal {
+                amplitude,
+                frequency,
+                phase,
+                offset,
+            } => vec![*amplitude, *frequency, *phase, *offset],
+            Coefficients::ArcTan {
+                scale,
+                shift,
+                amplitude,
+                offset,
+            } => vec![*scale, *shift, *amplitude, *offset],
+            Coefficients::Polynomial { coeffs } => coeffs.clone(),
+            Coefficients::Gaussian {
+                amplitude,
+                mean,
+                sigma,
+            } => vec![*amplitude, *mean, *sigma],
+            Coefficients::PiecewiseLinear { knots } => {
+                knots.iter().flat_map(|&(x, y)| [x, y]).collect()
+            }
+        }
+    }
 
-impl Display for Linear {
+    /// Number of fitted parameters, `k` in the `n - k` degrees of freedom
+    pub fn count(&self) -> usize {
+        self.values().len()
+    }
+}
+
+/// Any fitted function as a value, so fits can be saved and loaded without refitting.
+///
+/// Serialized the same way as [`Coefficients`], e.g. `{"family": "linear", "a": 2, "b": 1}`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+pub enum AnyFunction {
+    Linear(Linear),
+    LinearNoIntercept(LinearNoIntercept),
+    Quadratic(Quadratic),
+    Cubic(Cubic),
+    Exponent(Exponent),
+    Logarithm(Logrithm),
+    Log10(LogarithmBase10),
+    Power(Power),
+    Sinusoidal(Sinusoidal),
+    ArcTan(ArcTan),
+    Polynomial(Polynomial),
+    Hyperbolic(Hyperbolic),
+    SquareRoot(SquareRoot),
+    RobustLinear(RobustLinear),
+    Gaussian(Gaussian),
+    PiecewiseLinear(PiecewiseLinear),
+}
+
+impl AnyFunction {
+    fn inner(&self) -> &dyn Function {
+        match self {
+            AnyFunction::Linear(function) => function,
+            AnyFunction::LinearNoIntercept(function) => function,
+            AnyFunction::Quadratic(function) => function,
+            AnyFunction::Cubic(function) => function,
+            AnyFunction::Exponent(function) => function,
+            AnyFunction::Logarithm(function) => function,
+            AnyFunction::Log10(function) => function,
+            AnyFunction::Power(function) => function,
+            AnyFunction::Sinusoidal(function) => function,
+            AnyFunction::ArcTan(function) => function,
+            AnyFunction::Polynomial(function) => function,
+            AnyFunction::Hyperbolic(function) => function,
+            AnyFunction::SquareRoot(function) => function,
+            AnyFunction::RobustLinear(function) => function,
+            AnyFunction::Gaussian(function) => function,
+            AnyFunction::PiecewiseLinear(function) => function,
+        }
+    }
+}
+
+/// Fails only for piecewise linear knots [`PiecewiseLinear::new`] rejects
+impl TryFrom<Coefficients> for AnyFunction {
+    type Error = ApproximationError;
+
+    fn try_from(coefficients: Coefficients) -> Result<Self, Self::Error> {
+        Ok(match coefficients {
+            Coefficients::Linear { a, b } => AnyFunction::Linear(Linear { a, b }),
+            Coefficients::LinearNoIntercept { a } => {
+                AnyFunction::LinearNoIntercept(LinearNoIntercept { a })
+            }
+            Coefficients::Quadratic { a0, a1, a2 } => {
+                AnyFunction::Quadratic(Quadratic { a0, a1, a2 })
+            }
+            Coefficients::Cubic { a0, a1, a2, a3 } => AnyFunction::Cubic(Cubic { a0, a1, a2, a3 }),
+            Coefficients::Exponent { a0, a1 } => AnyFunction::Exponent(Exponent { a0, a1 }),
+            Coefficients::Logarithm { a0, a1 } => AnyFunction::Logarithm(Logrithm { a0, a1 }),
+            Coefficients::Log10 { a0, a1 } => AnyFunction::Log10(LogarithmBase10 { a0, a1 }),
+            Coefficients::Power { a0, a1 } => AnyFunction::Power(Power { a0, a1 }),
+            Coefficients::Sinusoidal {
+                amplitude,
+                frequency,
+                phase,
+                offset,
+            } => AnyFunction::Sinusoidal(Sinusoidal {
+                amplitude,
+                frequency,
+                phase,
+                offset,
+            }),
+            Coefficients::ArcTan {
+                scale,
+                shift,
+                amplitude,
+                offset,
+            } => AnyFunction::ArcTan(ArcTan {
+                scale,
+                shift,
+                amplitude,
+                offset,
+            }),
+            Coefficients::Polynomial { coeffs } => AnyFunction::Polynomial(Polynomial { coeffs }),
+            Coefficients::Hyperbolic { a, b } => AnyFunction::Hyperbolic(Hyperbolic { a, b }),
+            Coefficients::SquareRoot { a, b } => AnyFunction::SquareRoot(SquareRoot { a, b }),
+            Coefficients::RobustLinear { a, b } => AnyFunction::RobustLinear(RobustLinear { a, b }),
+            Coefficients::Gaussian {
+                amplitude,
+                mean,
+                sigma,
+            } => AnyFunction::Gaussian(Gaussian {
+                amplitude,
+                mean,
+                sigma,
+            }),
+            Coefficients::PiecewiseLinear { knots } => {
+                AnyFunction::PiecewiseLinear(PiecewiseLinear::new(knots)?)
+            }
+        })
+    }
+}
+
+impl Display for AnyFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Linear")?;
-        writeln!(f, "{}*x + {}", self.a, self.b)
+        self.inner().fmt(f)
     }
 }
 
-impl Function for Linear {
+impl Function for AnyFunction {
+    fn name(&self) -> &'static str {
+        self.inner().name()
+    }
+
     fn compute(&self, x: TNumber) -> TNumber {
-        self.a * x + self.b
+        self.inner().compute(x)
     }
-}
 
-impl MinimizedFunction for Linear {
-    fn new_minimized(points: &Vec<Point>) -> Linear {
-        let (sx, sxx, sy, sxy) = points
-            .iter()
-            .fold((0., 0., 0., 0.), |(sx, sxx, sy, sxy), Point { x, y }| {
-                (sx + x, sxx + x.powi(2), sy + y, sxy + x * y)
-            });
+    fn coefficients(&self) -> Coefficients {
+        self.inner().coefficients()
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        self.inner().formula(style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(self.clone())
+    }
 
-        let n = points.len() as f64;
-        let a = (sxy * n - sx * sy) / (sxx * n - sx.powi(2));
-        let b = (sxx * sy - sx * sxy) / (sxx * n - sx.powi(2));
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        self.inner().integrate(range)
+    }
 
-        Linear { a, b }
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.inner().derivative(x)
     }
 }
 
-pub struct Quadratic {
-    a0: TNumber,
-    a1: TNumber,
-    a2: TNumber,
+/// How coefficients are formatted, `Display` takes it from the formatter.
+///
+/// Alternate flag switches to scientific notation, e.g. `{:#.3}` gives `1.235e3`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumberStyle {
+    /// Digits after the decimal point, shortest exact representation when not set
+    pub precision: Option<usize>,
+    pub scientific: bool,
 }
 
-impl Display for Quadratic {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Quadratic")?;
-        writeln!(f, "{} + {}*x + {}*x^2", self.a0, self.a1, self.a2)
+impl NumberStyle {
+    fn of(f: &std::fmt::Formatter<'_>) -> Self {
+        NumberStyle {
+            precision: f.precision(),
+            scientific: f.alternate(),
+        }
     }
 }
 
-impl Function for Quadratic {
-    fn compute(&self, x: TNumber) -> TNumber {
-        self.a0 + self.a1 * x + self.a2 * x.powi(2)
+/// Family name line and then [`Function::formula`] in the style of the formatter
+fn write_function(function: &dyn Function, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "{}", function.name())?;
+    writeln!(f, "{}", function.formula(NumberStyle::of(f)))
+}
+
+/// Replaces `{name}` placeholders of a [`Family`] template with `values` in order
+fn fill_template(template: &str, values: &[TNumber], style: NumberStyle) -> String {
+    let mut values = values.iter();
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}').expect("placeholder is closed");
+        let value = values.next().expect("a value for every placeholder");
+        filled.push_str(&rest[..start]);
+        filled.push_str(&number(*value, style));
+        rest = &rest[end + 1..];
     }
+    filled.push_str(rest);
+    filled
 }
 
-impl MinimizedFunction for Quadratic {
-    fn new_minimized(points: &Vec<Point>) -> Self {
-        let mut matrix = General::<f64>::zero(3, 3);
-        let mut vector = Vector::<f64>::zero(3);
+/// Formats a coefficient with the requested style
+fn number(value: TNumber, style: NumberStyle) -> String {
+    match (style.precision, style.scientific) {
+        (Some(precision), false) => format!("{:.*}", precision, value),
+        (Some(precision), true) => format!("{:.*e}", precision, value),
+        (None, false) => value.to_string(),
+        (None, true) => format!("{:e}", value),
+    }
+}
+
+#[derive(Debug)]
+pub enum ApproximationError {
+    /// Normal equations have no unique solution,
+    /// e.g. there are too few distinct x values
+    SingularMatrix { family: String },
+    /// Family has more parameters than there are points
+    NotEnoughPoints {
+        family: String,
+        required: usize,
+        got: usize,
+    },
+    /// Fitted function is not finite at some of the points
+    Overflow { family: String },
+    /// Points outside of the family domain, `indices` are 1-based like in the output table
+    NonPositivePoints { family: String, indices: Vec<usize> },
+    /// Points do not form a peak, fitted parabola of `ln(y)` opens upwards
+    NotBellShaped { family: String },
+    /// Every point has the same x, so y is not a function of x
+    SingleX { value: TNumber },
+    /// Family cost grows too fast to fit this many points
+    TooManyPoints {
+        family: String,
+        limit: usize,
+        got: usize,
+    },
+}
+
+impl Display for ApproximationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApproximationError::SingularMatrix { family } => write!(
+                f,
+                "{} normal equations are singular, points do not determine the function",
+                family
+            ),
+            ApproximationError::NotEnoughPoints {
+                family,
+                required,
+                got,
+            } => write!(
+                f,
+                "{} needs at least {} points, got {}",
+                family, required, got
+            ),
+            ApproximationError::Overflow { family } => write!(
+                f,
+                "{} overflows over the x range of the points, values are not finite",
+                family
+            ),
+            ApproximationError::NonPositivePoints { family, indices } => {
+                let indices: Vec<_> = indices.iter().map(|index| index.to_string()).collect();
+                write!(
+                    f,
+                    "{} needs positive coordinates, points {} are not",
+                    family,
+                    indices.join(", ")
+                )
+            }
+            ApproximationError::NotBellShaped { family } => {
+                write!(f, "{} does not fit, points are not bell shaped", family)
+            }
+            ApproximationError::SingleX { value } => write!(
+                f,
+                "all points share x = {}; cannot fit y as a function of x",
+                value
+            ),
+            ApproximationError::TooManyPoints { family, limit, got } => write!(
+                f,
+                "{} supports at most {} points, got {}",
+                family, limit, got
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApproximationError {}
 
-        points.iter().for_each(|&Point { x, y }| {
-            matrix[[0, 0]] += 1.;
-            matrix[[0, 1]] += x;
-            matrix[[0, 2]] += x.powi(2);
-            matrix[[1, 0]] += x;
-            matrix[[1, 1]] += x.powi(2);
-            matrix[[1, 2]] += x.powi(3);
-            matrix[[2, 0]] += x.powi(2);
-            matrix[[2, 1]] += x.powi(3);
-            matrix[[2, 2]] += x.powi(4);
-
-            vector[0] += y;
-            vector[1] += x * y;
-            vector[2] += x * x * y;
+fn require_points(
+    family: &str,
+    points: &[Point],
+    required: usize,
+) -> Result<(), ApproximationError> {
+    if points.len() < required {
+        return Err(ApproximationError::NotEnoughPoints {
+            family: family.to_owned(),
+            required,
+            got: points.len(),
         });
+    }
 
-        let coeffs = matrix.solve(&vector).unwrap();
-        let a0 = coeffs[0];
-        let a1 = coeffs[1];
-        let a2 = coeffs[2];
+    Ok(())
+}
 
-        Quadratic { a0, a1, a2 }
+/// Fails with [`ApproximationError::SingleX`] when x of every point is the same,
+/// every family is degenerate then
+pub fn require_distinct_x(points: &[Point]) -> Result<(), ApproximationError> {
+    match points.split_first() {
+        Some((first, rest)) if rest.iter().all(|point| point.x == first.x) => {
+            Err(ApproximationError::SingleX { value: first.x })
+        }
+        _ => Ok(()),
     }
 }
 
-pub struct Cubic {
-    a0: TNumber,
-    a1: TNumber,
-    a2: TNumber,
-    a3: TNumber,
+/// Fits linear function to already transformed points of another family
+fn minimized_as(family: &str, points: &[Point]) -> Result<Linear, ApproximationError> {
+    require_points(family, points, 2)?;
+    Linear::new_minimized(points).map_err(|_| ApproximationError::SingularMatrix {
+        family: family.to_owned(),
+    })
 }
 
-impl Display for Cubic {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Cubic")?;
-        writeln!(
-            f,
-            "{} + {}*x + {}*x^2 + {}*x^3",
-            self.a0, self.a1, self.a2, self.a3
-        )
+pub trait MinimizedFunction: Sized {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError>;
+
+    /// Whether points lie in the domain of the family.
+    /// For example logarithm requires positive x
+    fn supports(_points: &[Point]) -> bool {
+        true
     }
 }
 
-impl Function for Cubic {
-    fn compute(&self, x: TNumber) -> TNumber {
-        self.a0 + self.a1 * x + self.a2 * x.powi(2) + self.a3 * x.powi(3)
+impl Clone for Box<dyn Function> {
+    fn clone(&self) -> Self {
+        self.clone_box()
     }
 }
 
-impl MinimizedFunction for Cubic {
-    fn new_minimized(points: &Vec<Point>) -> Self {
-        let mut matrix = General::<f64>::zero(4, 4);
-        let mut vector = Vector::<f64>::zero(4);
-
-        points.iter().for_each(|&Point { x, y }| {
-            matrix[[0, 0]] += 1.;
-            matrix[[0, 1]] += x;
-            matrix[[0, 2]] += x.powi(2);
-            matrix[[0, 3]] += x.powi(3);
-            matrix[[1, 0]] += x;
-            matrix[[1, 1]] += x.powi(2);
-            matrix[[1, 2]] += x.powi(3);
-            matrix[[1, 3]] += x.powi(4);
-            matrix[[2, 0]] += x.powi(2);
-            matrix[[2, 1]] += x.powi(3);
-            matrix[[2, 2]] += x.powi(4);
-            matrix[[2, 3]] += x.powi(5);
-            matrix[[3, 0]] += x.powi(3);
-            matrix[[3, 1]] += x.powi(4);
-            matrix[[3, 2]] += x.powi(5);
-            matrix[[3, 3]] += x.powi(6);
-
-            vector[0] += y;
-            vector[1] += x * y;
-            vector[2] += x.powi(2) * y;
-            vector[3] += x.powi(3) * y;
-        });
+type Approximation = Result<Box<dyn Function>, ApproximationError>;
 
-        let coeffs = matrix.solve(&vector).unwrap();
-        let a0 = coeffs[0];
-        let a1 = coeffs[1];
-        let a2 = coeffs[2];
-        let a3 = coeffs[3];
+fn minimized<F: MinimizedFunction + Function>(points: &[Point]) -> Option<Approximation> {
+    if !F::supports(points) {
+        return None;
+    }
+
+    Some(F::new_minimized(points).map(|function| Box::new(function) as Box<dyn Function>))
+}
+
+/// Degree of the highest named polynomial family ([`Cubic`])
+pub const DEFAULT_MAX_DEGREE: usize = 3;
+
+// ln(0) = -inf
+// so we need to account for point (0, y)
+pub const DEFAULT_ZERO_EPSILON: TNumber = 0.000001;
+
+/// Kind of function fitted by [`create_approximations`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Family {
+    Linear,
+    /// Line through the origin
+    LinearNoIntercept,
+    /// Line resistant to outliers, see [`RobustLinear`]
+    RobustLinear,
+    Quadratic,
+    Cubic,
+    Exponent,
+    Logarithm,
+    Log10,
+    Power,
+    Sinusoidal,
+    /// Saturating curve, see [`ArcTan`]
+    ArcTan,
+    Hyperbolic,
+    SquareRoot,
+    /// Bell curve, see [`Gaussian`]
+    Gaussian,
+    /// Polynomials of degree above [`DEFAULT_MAX_DEGREE`]
+    Polynomial,
+}
+
+impl Family {
+    /// Every family in the order they are fitted
+    pub const ALL: [Family; 15] = [
+        Family::Linear,
+        Family::LinearNoIntercept,
+        Family::RobustLinear,
+        Family::Quadratic,
+        Family::Cubic,
+        Family::Exponent,
+        Family::Logarithm,
+        Family::Log10,
+        Family::Power,
+        Family::Sinusoidal,
+        Family::ArcTan,
+        Family::Hyperbolic,
+        Family::SquareRoot,
+        Family::Gaussian,
+        Family::Polynomial,
+    ];
 
-        Cubic { a0, a1, a2, a3 }
+    /// Lowercase name used to select the family
+    pub fn key(self) -> &'static str {
+        match self {
+            Family::Linear => "linear",
+            Family::LinearNoIntercept => "linear_no_intercept",
+            Family::RobustLinear => "robust_linear",
+            Family::Quadratic => "quadratic",
+            Family::Cubic => "cubic",
+            Family::Exponent => "exponent",
+            Family::Logarithm => "logarithm",
+            Family::Log10 => "log10",
+            Family::Power => "power",
+            Family::Sinusoidal => "sinusoidal",
+            Family::ArcTan => "arc_tan",
+            Family::Hyperbolic => "hyperbolic",
+            Family::SquareRoot => "square_root",
+            Family::Gaussian => "gaussian",
+            Family::Polynomial => "polynomial",
+        }
+    }
+
+    /// Polynomial degrees the family fits when degrees above `max_degree` are excluded,
+    /// empty when none is left. `None` for families that are not polynomials
+    pub fn degrees(self, max_degree: usize) -> Option<RangeInclusive<usize>> {
+        match self {
+            Family::Quadratic => Some(2..=max_degree.min(2)),
+            Family::Cubic => Some(3..=max_degree.min(3)),
+            Family::Polynomial => Some(DEFAULT_MAX_DEGREE + 1..=max_degree),
+            _ => None,
+        }
+    }
+
+    /// Equation with `{name}` in place of every parameter, the fitted functions print it
+    /// with their numbers filled in, see [`fill_template`]
+    fn template(self) -> &'static str {
+        match self {
+            Family::Linear => "{a}*x + {b}",
+            Family::LinearNoIntercept => "{a}*x",
+            Family::RobustLinear => "{a}*x + {b}",
+            Family::Quadratic => "{a0} + {a1}*x + {a2}*x^2",
+            Family::Cubic => "{a0} + {a1}*x + {a2}*x^2 + {a3}*x^3",
+            Family::Exponent => "e^({a0}*x + {a1})",
+            Family::Logarithm => "{a0}*ln(x) + {a1}",
+            Family::Log10 => "{a0}*log10(x) + {a1}",
+            // c = e^a0 and k = a1 of the coefficients
+            Family::Power => "{c}*x^{k}",
+            Family::Sinusoidal => "{amplitude}*sin({frequency}*x + {phase}) + {offset}",
+            Family::ArcTan => "{amplitude}*atan({scale}*x + {shift}) + {offset}",
+            Family::Hyperbolic => "{a}/x + {b}",
+            Family::SquareRoot => "{a}*sqrt(x) + {b}",
+            Family::Gaussian => "{amplitude}*e^(-(x - {mean})^2 / (2*{sigma}^2))",
+            // written term by term for the actual degree, see `Polynomial::formula`
+            Family::Polynomial => "{a0} + {a1}*x + ... + {an}*x^n",
+        }
+    }
+
+    /// General formula with parameter names in place of the numbers,
+    /// the same equation the fitted function prints
+    pub fn formula(self) -> String {
+        self.template().replace(['{', '}'], "")
     }
 }
 
-pub struct Exponent {
-    a0: TNumber,
-    a1: TNumber,
+impl FromStr for Family {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Family::ALL
+            .into_iter()
+            .find(|family| family.key() == s)
+            .ok_or_else(|| {
+                let known: Vec<_> = Family::ALL.iter().map(|family| family.key()).collect();
+                format!(
+                    "unknown family `{}`, expected one of: {}",
+                    s,
+                    known.join(", ")
+                )
+            })
+    }
 }
 
-impl Display for Exponent {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Exponent")?;
-        writeln!(f, "e^({}*x + {})", self.a0, self.a1)
+#[derive(Clone, Debug)]
+pub struct FitOptions {
+    /// Highest polynomial degree fitted, [`Quadratic`] and [`Cubic`] are skipped below theirs
+    /// and polynomials above [`DEFAULT_MAX_DEGREE`] are fitted up to it, see [`Family::degrees`]
+    pub max_degree: usize,
+    /// Zero coordinates are replaced with this before fitting logarithm based families
+    pub zero_epsilon: TNumber,
+    /// Replace zero x with [`FitOptions::zero_epsilon`]
+    pub zero_fix_x: bool,
+    /// Replace zero y with [`FitOptions::zero_epsilon`]
+    pub zero_fix_y: bool,
+    /// Only these families are fitted
+    pub families: Vec<Family>,
+    /// Fit polynomials with x mapped to `[-1, 1]`, see [`Polynomial::new_minimized_scaled`]
+    pub scaled_basis: bool,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        FitOptions {
+            max_degree: DEFAULT_MAX_DEGREE,
+            zero_epsilon: DEFAULT_ZERO_EPSILON,
+            zero_fix_x: true,
+            zero_fix_y: true,
+            families: Family::ALL.to_vec(),
+            scaled_basis: false,
+        }
     }
 }
 
-impl Function for Exponent {
-    fn compute(&self, x: TNumber) -> TNumber {
-        (self.a0 * x + self.a1).exp()
+/// Replaces exactly zero coordinates with [`FitOptions::zero_epsilon`]
+/// on the axes the options enable it for
+fn without_zeros(points: &[Point], options: &FitOptions) -> Vec<Point> {
+    let fix = |value: TNumber, enabled: bool| {
+        if enabled && value == 0. {
+            options.zero_epsilon
+        } else {
+            value
+        }
+    };
+    points
+        .iter()
+        .map(|&Point { x, y, w }| Point {
+            x: fix(x, options.zero_fix_x),
+            y: fix(y, options.zero_fix_y),
+            w,
+        })
+        .collect()
+}
+
+/// Fits every function family to the points.
+///
+/// Families which can not represent the points (see [`MinimizedFunction::supports`])
+/// or fail to fit are skipped, so a single non-positive value never produces a garbage fit
+/// and a singular system never aborts the program.
+pub fn create_approximations(points: &[Point], options: &FitOptions) -> Vec<Box<dyn Function>> {
+    try_create_approximations(points, options)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Same as [`create_approximations`], but keeps the reason of every failed fit
+pub fn try_create_approximations(points: &[Point], options: &FitOptions) -> Vec<Approximation> {
+    // a single reason instead of every family failing on its own
+    if let Err(error) = require_distinct_x(points) {
+        return vec![Err(error)];
     }
+
+    // only logarithm based families need zeros replaced,
+    // the rest see the true data
+    let log_points = without_zeros(points, options);
+
+    // every polynomial family solves its system from the same power sums
+    let top_degree = options.max_degree.max(DEFAULT_MAX_DEGREE);
+    let (moments, basis) = if options.scaled_basis {
+        let (scaled, basis) = ScaledBasis::new(points);
+        (Moments::new(&scaled, top_degree), Some(basis))
+    } else {
+        (Moments::new(points, top_degree), None)
+    };
+    let polynomial = |degree| {
+        let polynomial = Polynomial::from_moments(&moments, degree);
+        match basis {
+            Some(basis) => polynomial.and_then(|polynomial| basis.unscale(polynomial)),
+            None => polynomial,
+        }
+    };
+
+    let fit =
+        |family: Family| -> Vec<Option<Approximation>> {
+            match family {
+                Family::Linear => vec![minimized::<Linear>(points)],
+                Family::LinearNoIntercept => vec![minimized::<LinearNoIntercept>(points)],
+                Family::RobustLinear => vec![minimized::<RobustLinear>(points)],
+                Family::Quadratic => family
+                    .degrees(options.max_degree)
+                    .into_iter()
+                    .flatten()
+                    .map(|degree| {
+                        Some(polynomial(degree).map(|polynomial| {
+                            Box::new(Quadratic::from(polynomial)) as Box<dyn Function>
+                        }))
+                    })
+                    .collect(),
+                Family::Cubic => family
+                    .degrees(options.max_degree)
+                    .into_iter()
+                    .flatten()
+                    .map(|degree| {
+                        Some(polynomial(degree).map(|polynomial| {
+                            Box::new(Cubic::from(polynomial)) as Box<dyn Function>
+                        }))
+                    })
+                    .collect(),
+                Family::Exponent => vec![minimized::<Exponent>(&log_points)],
+                Family::Logarithm => vec![minimized::<Logrithm>(&log_points)],
+                Family::Log10 => vec![minimized::<LogarithmBase10>(&log_points)],
+                Family::Power => vec![minimized::<Power>(&log_points)],
+                Family::Sinusoidal => vec![minimized::<Sinusoidal>(points)],
+                Family::ArcTan => vec![minimized::<ArcTan>(points)],
+                Family::Hyperbolic => vec![minimized::<Hyperbolic>(points)],
+                Family::SquareRoot => vec![minimized::<SquareRoot>(points)],
+                Family::Gaussian => vec![minimized::<Gaussian>(points)],
+                Family::Polynomial => family
+                    .degrees(options.max_degree)
+                    .into_iter()
+                    .flatten()
+                    .map(|degree| {
+                        Some(
+                            polynomial(degree)
+                                .map(|polynomial| Box::new(polynomial) as Box<dyn Function>),
+                        )
+                    })
+                    .collect(),
+            }
+        };
+
+    let families: Vec<_> = Family::ALL
+        .into_iter()
+        .filter(|family| options.families.contains(family))
+        .collect();
+
+    // indexed parallel collect keeps the order of families
+    #[cfg(feature = "parallel")]
+    let fitted: Vec<_> = {
+        use rayon::prelude::*;
+        families.into_par_iter().map(fit).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let fitted: Vec<_> = families.into_iter().map(fit).collect();
+
+    fitted.into_iter().flatten().flatten().collect()
 }
 
-impl MinimizedFunction for Exponent {
-    fn new_minimized(points: &Vec<Point>) -> Self {
-        let points: Vec<_> = points
+/// `(point, φ(x), ε)` for every point, where `ε = φ(x) - y`
+pub fn compute_deviation(
+    points: &[Point],
+    function: &dyn Function,
+) -> Vec<(Point, TNumber, TNumber)> {
+    points
+        .iter()
+        .map(|&point| {
+            let phi = function.compute(point.x);
+            let epsilon = phi - point.y;
+            (point, phi, epsilon)
+        })
+        .collect()
+}
+
+/// Savitzky–Golay style smoothing: y of every point is replaced with the value of
+/// a polynomial of `degree` fitted to the `window` points closest in x order,
+/// windows are shifted inwards at the ends. Result keeps the order of `points`
+pub fn smooth(
+    points: &[Point],
+    window: usize,
+    degree: usize,
+) -> Result<Vec<Point>, ApproximationError> {
+    let family = format!("Smoothing with {}", polynomial_family(degree));
+    if window > points.len() {
+        return Err(ApproximationError::NotEnoughPoints {
+            family,
+            required: window,
+            got: points.len(),
+        });
+    }
+    if window <= degree {
+        return Err(ApproximationError::NotEnoughPoints {
+            family,
+            required: degree + 1,
+            got: window,
+        });
+    }
+
+    let mut order: Vec<_> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| points[a].x.total_cmp(&points[b].x));
+
+    let mut smoothed = points.to_vec();
+    for (position, &index) in order.iter().enumerate() {
+        let start = position
+            .saturating_sub(window / 2)
+            .min(points.len() - window);
+        let neighbours: Vec<_> = order[start..start + window]
             .iter()
-            .map(|Point { x, y }| Point { x: *x, y: y.ln() })
+            .map(|&neighbour| points[neighbour])
             .collect();
+        let polynomial = Polynomial::new_minimized(&neighbours, degree).map_err(|_| {
+            ApproximationError::SingularMatrix {
+                family: family.clone(),
+            }
+        })?;
+        smoothed[index].y = polynomial.compute(points[index].x);
+    }
 
-        let Linear { a: a0, b: a1 } = Linear::new_minimized(&points);
+    Ok(smoothed)
+}
+
+/// How well a function fits the points
+#[derive(Clone, Debug)]
+pub struct FitMetrics {
+    /// Residual standard deviation with `n - k` degrees of freedom,
+    /// `k` is the amount of coefficients. `NAN` when `n <= k`
+    pub standard_deviation: TNumber,
+    pub r_squared: TNumber,
+    /// See [`compute_deviation`]
+    pub deviations: Vec<(Point, TNumber, TNumber)>,
+}
+
+impl FitMetrics {
+    /// [`FitMetrics::new`] of every function, in the same order
+    pub fn for_all(points: &[Point], functions: &[Box<dyn Function>]) -> Vec<Self> {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            functions
+                .par_iter()
+                .map(|function| FitMetrics::new(points, function.as_ref()))
+                .collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            functions
+                .iter()
+                .map(|function| FitMetrics::new(points, function.as_ref()))
+                .collect()
+        }
+    }
+
+    pub fn new(points: &[Point], function: &dyn Function) -> Self {
+        let deviations = compute_deviation(points, function);
+        let parameters = function.coefficients().count();
 
-        Exponent { a0, a1 }
+        // divided by `n - k` so extra parameters are not free
+        let standard_deviation = if points.len() > parameters {
+            let epsilon_sum: TNumber = deviations
+                .iter()
+                .map(|(_, _, epsilon)| epsilon.powi(2))
+                .sum();
+            (epsilon_sum / (points.len() - parameters) as TNumber).sqrt()
+        } else {
+            TNumber::NAN
+        };
+
+        FitMetrics {
+            standard_deviation,
+            r_squared: function.r_squared(points),
+            deviations,
+        }
     }
 }
 
-pub struct Logrithm {
-    a0: TNumber,
-    a1: TNumber,
+/// How the best function is chosen
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Criterion {
+    /// Smallest standard deviation
+    #[default]
+    Rms,
+    /// Smallest Akaike information criterion `n*ln(RSS/n) + 2k`
+    Aic,
 }
 
-impl Display for Logrithm {
+impl FromStr for Criterion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rms" => Ok(Criterion::Rms),
+            "aic" => Ok(Criterion::Aic),
+            _ => Err(format!(
+                "unknown criterion `{}`, expected one of: rms, aic",
+                s
+            )),
+        }
+    }
+}
+
+/// AIC difference below which the runner-up is as plausible as the best,
+/// the usual rule of thumb
+pub const AIC_AMBIGUITY: TNumber = 2.;
+
+impl Criterion {
+    /// Score of a fit, smaller is better
+    pub fn score(self, function: &dyn Function, metrics: &FitMetrics) -> TNumber {
+        match self {
+            Criterion::Rms => metrics.standard_deviation,
+            Criterion::Aic => aic(&metrics.deviations, function.coefficients().count()),
+        }
+    }
+
+    /// Scores closer than this are a tie, see [`TIE_TOLERANCE`].
+    /// AIC is a log-likelihood, so it needs no scale of the data
+    fn tie_tolerance(self, deviations: &[(Point, TNumber, TNumber)]) -> TNumber {
+        match self {
+            Criterion::Rms => TIE_TOLERANCE * largest_y(deviations),
+            Criterion::Aic => TIE_TOLERANCE,
+        }
+    }
+
+    /// Whether a runner-up scoring `runner_up` fits almost as well as the `best` score.
+    ///
+    /// `threshold` is a fraction of the best standard deviation,
+    /// AIC compares absolute differences against [`AIC_AMBIGUITY`] instead
+    pub fn is_ambiguous(self, best: TNumber, runner_up: TNumber, threshold: TNumber) -> bool {
+        match self {
+            Criterion::Rms => runner_up - best <= threshold * best,
+            Criterion::Aic => runner_up - best < AIC_AMBIGUITY,
+        }
+    }
+}
+
+fn largest_y(deviations: &[(Point, TNumber, TNumber)]) -> TNumber {
+    deviations
+        .iter()
+        .fold(0., |scale: TNumber, (point, _, _)| scale.max(point.y.abs()))
+}
+
+/// Akaike information criterion `n*ln(RSS/n) + 2k`, smaller is better.
+///
+/// Residuals are floored at [`TIE_TOLERANCE`] of the largest `|y|`, so every exact fit
+/// has the same RSS and only its parameter count decides instead of rounding noise
+pub fn aic(deviations: &[(Point, TNumber, TNumber)], parameters: usize) -> TNumber {
+    let n = deviations.len() as TNumber;
+    let rss: TNumber = deviations
+        .iter()
+        .map(|(_, _, epsilon)| epsilon.powi(2))
+        .sum();
+    let noise = n * (TIE_TOLERANCE * largest_y(deviations)).powi(2);
+    n * (rss.max(noise) / n).ln() + 2. * parameters as TNumber
+}
+
+/// Indices of the functions that may win by `criterion`, best first, and the score of every one.
+///
+/// NaN or infinite deviation means the fit blew up somewhere, such functions never win.
+/// A function with as many parameters as points has no deviation either,
+/// it is ranked as an exact fit only when passing through every point and no function
+/// with two or more parameters has a degree of freedom left, e.g. a line through two points
+/// must not lose to the line through the origin, the only one with a deviation.
+/// Of functions tied up to rounding the one with fewer parameters comes first,
+/// e.g. a cubic on exact quadratic points must not win by rounding noise
+pub fn rank(
+    functions: &[Box<dyn Function>],
+    metrics: &[FitMetrics],
+    criterion: Criterion,
+) -> (Vec<usize>, Vec<TNumber>) {
+    let parameters: Vec<usize> = functions
+        .iter()
+        .map(|function| function.coefficients().count())
+        .collect();
+    let has_freedom = |index: usize| parameters[index] < metrics[index].deviations.len();
+    let interpolates = |index: usize| {
+        let deviations = &metrics[index].deviations;
+        let tolerance = TIE_TOLERANCE * largest_y(deviations);
+        parameters[index] == deviations.len()
+            && deviations
+                .iter()
+                .all(|(_, _, epsilon)| epsilon.abs() <= tolerance)
+    };
+    let general_has_freedom = (0..metrics.len()).any(|index| {
+        parameters[index] >= 2
+            && has_freedom(index)
+            && metrics[index].standard_deviation.is_finite()
+    });
+    let exact: Vec<bool> = (0..metrics.len())
+        .map(|index| !general_has_freedom && interpolates(index))
+        .collect();
+
+    let scores: Vec<TNumber> = functions
+        .iter()
+        .zip(metrics)
+        .zip(&exact)
+        .map(|((function, metrics), &exact)| match criterion {
+            Criterion::Rms if exact => 0.,
+            _ => criterion.score(function.as_ref(), metrics),
+        })
+        .collect();
+    let mut ranking: Vec<usize> = (0..metrics.len())
+        .filter(|&index| metrics[index].standard_deviation.is_finite() || exact[index])
+        .collect();
+    ranking.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+    if let Some(&first) = ranking.first() {
+        let tolerance = criterion.tie_tolerance(&metrics[first].deviations);
+        prefer_fewer_parameters(&mut ranking, functions, &scores, tolerance);
+    }
+    (ranking, scores)
+}
+
+/// Standard deviations closer than this fraction of the largest `|y|` are a tie,
+/// e.g. exact quadratic points fitted by a cubic differ from the quadratic only by rounding
+pub const TIE_TOLERANCE: TNumber = 1e-9;
+
+/// Moves the function with the fewest parameters among those scoring within `tolerance`
+/// of the first one to the front of `ranking`, the rest keep their order.
+///
+/// `ranking` holds indices into `functions` and `scores`, best first
+fn prefer_fewer_parameters(
+    ranking: &mut [usize],
+    functions: &[Box<dyn Function>],
+    scores: &[TNumber],
+    tolerance: TNumber,
+) {
+    let Some(&first) = ranking.first() else {
+        return;
+    };
+    let best = scores[first];
+
+    let simplest = ranking
+        .iter()
+        .enumerate()
+        .filter(|&(_, &index)| (scores[index] - best).abs() <= tolerance)
+        .min_by_key(|&(position, &index)| (functions[index].coefficients().count(), position))
+        .map(|(position, _)| position);
+    if let Some(position) = simplest {
+        ranking[..=position].rotate_right(1);
+    }
+}
+
+/// Pearson correlation coefficient of the points.
+///
+/// Returns `NAN` when it is undefined (all x or all y are equal).
+pub fn pearson_correlation(points: &[Point]) -> TNumber {
+    let (sx, sy, sxy, sxx, syy) = points.iter().fold(
+        (0., 0., 0., 0., 0.),
+        |(sx, sy, sxy, sxx, syy), Point { x, y, .. }| {
+            (
+                sx + x,
+                sy + y,
+                sxy + x * y,
+                sxx + x.powi(2),
+                syy + y.powi(2),
+            )
+        },
+    );
+
+    let n = points.len() as f64;
+    let denominator = ((n * sxx - sx.powi(2)) * (n * syy - sy.powi(2))).sqrt();
+    if denominator == 0. {
+        return f64::NAN;
+    }
+
+    (n * sxy - sx * sy) / denominator
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Linear {
+    /// Multiplier
+    a: TNumber,
+    /// Addition
+    b: TNumber,
+}
+
+// special thanks to Lannee for implementation of all minimization rutines
+
+impl Display for Linear {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Natural Logarithm")?;
-        writeln!(f, "{} * ln(x) + {}", self.a0, self.a1)
+        write_function(self, f)
     }
 }
 
-impl Function for Logrithm {
+impl Function for Linear {
+    fn name(&self) -> &'static str {
+        "Linear"
+    }
+
     fn compute(&self, x: TNumber) -> TNumber {
-        self.a0 * x.ln() + self.a1
+        self.a * x + self.b
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Linear {
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::Linear.template(), &[self.a, self.b], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        polynomial_integral(&[self.b, self.a], range)
+    }
+
+    fn derivative(&self, _x: TNumber) -> TNumber {
+        self.a
     }
 }
 
-impl MinimizedFunction for Logrithm {
-    fn new_minimized(points: &Vec<Point>) -> Self {
-        let points_mapped: Vec<_> = points
+impl Linear {
+    /// Standard errors of `a` and `b` from the residual variance and x spread.
+    ///
+    /// `None` when there are not more than 2 points, so residual variance is undefined,
+    /// or all x are equal.
+    pub fn standard_errors(&self, points: &[Point]) -> Option<(TNumber, TNumber)> {
+        if points.len() <= 2 {
+            return None;
+        }
+
+        let weights: TNumber = points.iter().map(|point| point.w).sum();
+        let mean_x = points
             .iter()
-            .map(|&Point { x, y }| Point { x: x.ln(), y })
-            .collect();
+            .map(|point| point.w * point.x)
+            .sum::<TNumber>()
+            / weights;
+        let (ss_res, ss_x) = points
+            .iter()
+            .fold((0., 0.), |(ss_res, ss_x), &Point { x, y, w }| {
+                (
+                    ss_res + w * (y - self.compute(x)).powi(2),
+                    ss_x + w * (x - mean_x).powi(2),
+                )
+            });
+        if ss_x < SUM_OF_SQUARES_TOLERANCE {
+            return None;
+        }
 
-        let Linear { a: a0, b: a1 } = Linear::new_minimized(&points_mapped);
+        let variance = ss_res / (points.len() - 2) as TNumber;
+        let a = (variance / ss_x).sqrt();
+        let b = (variance * (1. / weights + mean_x.powi(2) / ss_x)).sqrt();
+        Some((a, b))
+    }
+}
 
-        Logrithm { a0, a1 }
+impl MinimizedFunction for Linear {
+    fn new_minimized(points: &[Point]) -> Result<Linear, ApproximationError> {
+        let mut accumulator = LinearAccumulator::default();
+        points.iter().for_each(|&point| accumulator.push(point));
+        accumulator.finish()
     }
 }
 
-pub struct Power {
+/// Running sums of [`Linear`] fit, so points can be fitted while they are read
+/// without keeping them in memory
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinearAccumulator {
+    count: usize,
+    // weighted sums, `n` is the sum of weights
+    n: TNumber,
+    sx: TNumber,
+    sxx: TNumber,
+    sy: TNumber,
+    sxy: TNumber,
+}
+
+impl LinearAccumulator {
+    pub fn push(&mut self, Point { x, y, w }: Point) {
+        self.count += 1;
+        self.n += w;
+        self.sx += w * x;
+        self.sxx += w * x.powi(2);
+        self.sy += w * y;
+        self.sxy += w * x * y;
+    }
+
+    /// Same coefficients as [`Linear::new_minimized`] of the pushed points
+    pub fn finish(self) -> Result<Linear, ApproximationError> {
+        let LinearAccumulator {
+            count,
+            n,
+            sx,
+            sxx,
+            sy,
+            sxy,
+        } = self;
+        if count < 2 {
+            return Err(ApproximationError::NotEnoughPoints {
+                family: "Linear".to_owned(),
+                required: 2,
+                got: count,
+            });
+        }
+
+        let determinant = sxx * n - sx.powi(2);
+        if determinant == 0. {
+            return Err(ApproximationError::SingularMatrix {
+                family: "Linear".to_owned(),
+            });
+        }
+
+        let a = (sxy * n - sx * sy) / determinant;
+        let b = (sxx * sy - sx * sxy) / determinant;
+
+        Ok(Linear { a, b })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Quadratic {
     a0: TNumber,
     a1: TNumber,
+    a2: TNumber,
 }
 
-impl Display for Power {
+impl Display for Quadratic {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Power")?;
-        writeln!(f, "{}*e*x^{}", self.a0, self.a1)
+        write_function(self, f)
     }
 }
 
-impl Function for Power {
+impl Function for Quadratic {
+    fn name(&self) -> &'static str {
+        "Quadratic"
+    }
+
     fn compute(&self, x: TNumber) -> TNumber {
-        self.a0.exp() * x.powf(self.a1)
+        self.a0 + self.a1 * x + self.a2 * x.powi(2)
     }
-}
 
-impl MinimizedFunction for Power {
-    fn new_minimized(points: &Vec<Point>) -> Self {
-        let points_mapped: Vec<_> = points
-            .iter()
-            .map(|Point { x, y }| Point {
-                x: x.ln(),
-                y: y.ln(),
-            })
-            .collect();
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Quadratic {
+            a0: self.a0,
+            a1: self.a1,
+            a2: self.a2,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(
+            Family::Quadratic.template(),
+            &[self.a0, self.a1, self.a2],
+            style,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
 
-        let Linear { a: a0, b: a1 } = Linear::new_minimized(&points_mapped);
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        polynomial_integral(&[self.a0, self.a1, self.a2], range)
+    }
 
-        Power { a0, a1 }
+    fn derivative(&self, x: TNumber) -> TNumber {
+        polynomial_derivative(&[self.a0, self.a1, self.a2], x)
+    }
+}
+
+impl MinimizedFunction for Quadratic {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        Polynomial::new_minimized(points, 2).map(Quadratic::from)
+    }
+}
+
+impl From<Polynomial> for Quadratic {
+    /// Expects polynomial of degree 2
+    fn from(Polynomial { coeffs }: Polynomial) -> Self {
+        Quadratic {
+            a0: coeffs[0],
+            a1: coeffs[1],
+            a2: coeffs[2],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Cubic {
+    a0: TNumber,
+    a1: TNumber,
+    a2: TNumber,
+    a3: TNumber,
+}
+
+impl Display for Cubic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for Cubic {
+    fn name(&self) -> &'static str {
+        "Cubic"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a0 + self.a1 * x + self.a2 * x.powi(2) + self.a3 * x.powi(3)
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Cubic {
+            a0: self.a0,
+            a1: self.a1,
+            a2: self.a2,
+            a3: self.a3,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(
+            Family::Cubic.template(),
+            &[self.a0, self.a1, self.a2, self.a3],
+            style,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        polynomial_integral(&[self.a0, self.a1, self.a2, self.a3], range)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        polynomial_derivative(&[self.a0, self.a1, self.a2, self.a3], x)
+    }
+}
+
+impl MinimizedFunction for Cubic {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        Polynomial::new_minimized(points, 3).map(Cubic::from)
+    }
+}
+
+impl From<Polynomial> for Cubic {
+    /// Expects polynomial of degree 3
+    fn from(Polynomial { coeffs }: Polynomial) -> Self {
+        Cubic {
+            a0: coeffs[0],
+            a1: coeffs[1],
+            a2: coeffs[2],
+            a3: coeffs[3],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Polynomial {
+    /// Coefficient at `x^i` is `coeffs[i]`
+    coeffs: Vec<TNumber>,
+}
+
+impl Display for Polynomial {
+    /// Name line tells the degree, unlike [`Function::name`]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", polynomial_family(self.degree()))?;
+        writeln!(f, "{}", self.formula(NumberStyle::of(f)))
+    }
+}
+
+impl Function for Polynomial {
+    /// Same for every degree, see [`polynomial_family`] for a distinct one
+    fn name(&self) -> &'static str {
+        "Polynomial"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(0., |result, coeff| result * x + coeff)
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Polynomial {
+            coeffs: self.coeffs.clone(),
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        let terms: Vec<_> = self
+            .coeffs
+            .iter()
+            .enumerate()
+            .map(|(power, &coeff)| match power {
+                0 => number(coeff, style),
+                1 => format!("{}*x", number(coeff, style)),
+                _ => format!("{}*x^{}", number(coeff, style), power),
+            })
+            .collect();
+        terms.join(" + ")
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(self.clone())
+    }
+
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        polynomial_integral(&self.coeffs, range)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        polynomial_derivative(&self.coeffs, x)
+    }
+}
+
+/// Weighted power sums of the points, collected in a single pass so
+/// polynomials of every degree up to `degree` can share them
+#[derive(Clone, Debug)]
+pub struct Moments {
+    degree: usize,
+    count: usize,
+    /// `Σ w*x^k` for `k = 0..=2*degree`
+    x: Vec<TNumber>,
+    /// `Σ w*x^k*y` for `k = 0..=degree`
+    xy: Vec<TNumber>,
+}
+
+impl Moments {
+    /// Takes any iterator of points, it is walked exactly once
+    pub fn new<'a>(points: impl IntoIterator<Item = &'a Point>, degree: usize) -> Self {
+        let mut x_moments = vec![0.; 2 * degree + 1];
+        let mut xy_moments = vec![0.; degree + 1];
+        let mut count = 0;
+        points.into_iter().for_each(|&Point { x, y, w }| {
+            count += 1;
+            let mut power = w;
+            for (k, moment) in x_moments.iter_mut().enumerate() {
+                *moment += power;
+                if k <= degree {
+                    xy_moments[k] += power * y;
+                }
+                power *= x;
+            }
+        });
+
+        Moments {
+            degree,
+            count,
+            x: x_moments,
+            xy: xy_moments,
+        }
+    }
+
+    /// Highest polynomial degree these moments can fit
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// Normal equations `matrix * coeffs = rhs` for polynomial of the given degree,
+    /// `matrix` is stored by rows
+    ///
+    /// # Panics
+    /// When `degree` is above [`Moments::degree`]
+    pub fn normal_equations(&self, degree: usize) -> (Vec<Vec<TNumber>>, Vec<TNumber>) {
+        assert!(
+            degree <= self.degree,
+            "moments collected up to degree {}, got {}",
+            self.degree,
+            degree
+        );
+        let size = degree + 1;
+
+        // normal matrix is symmetric with x^(i + j) sums on each anti-diagonal
+        let matrix = (0..size).map(|i| self.x[i..i + size].to_vec()).collect();
+        (matrix, self.xy[..size].to_vec())
+    }
+}
+
+/// Name of polynomial family of the given degree
+pub fn polynomial_family(degree: usize) -> String {
+    match degree {
+        1 => "Linear".to_owned(),
+        2 => "Quadratic".to_owned(),
+        3 => "Cubic".to_owned(),
+        _ => format!("Polynomial of degree {}", degree),
+    }
+}
+
+impl Polynomial {
+    pub fn degree(&self) -> usize {
+        self.coeffs.len() - 1
+    }
+
+    /// See [`Moments::normal_equations`]
+    pub fn normal_equations(points: &[Point], degree: usize) -> (Vec<Vec<TNumber>>, Vec<TNumber>) {
+        Moments::new(points, degree).normal_equations(degree)
+    }
+
+    /// Solves normal equations for polynomial of the given degree
+    pub fn new_minimized(points: &[Point], degree: usize) -> Result<Self, ApproximationError> {
+        Polynomial::from_moments(&Moments::new(points, degree), degree)
+    }
+
+    /// Condition number `‖A‖₁·‖A⁻¹‖₁` of the normal matrix solved for the given degree,
+    /// infinite when it is singular. Large values mean the coefficients are mostly rounding noise
+    pub fn condition_number(points: &[Point], degree: usize, scaled_basis: bool) -> TNumber {
+        let (rows, _) = if scaled_basis {
+            Polynomial::normal_equations(&ScaledBasis::new(points).0, degree)
+        } else {
+            Polynomial::normal_equations(points, degree)
+        };
+
+        let size = degree + 1;
+        let mut matrix = General::<f64>::zero(size, size);
+        for (i, row) in rows.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                matrix[[i, j]] = value;
+            }
+        }
+        let Ok(inverse) = matrix.inv() else {
+            return TNumber::INFINITY;
+        };
+
+        let norm = |matrix: &General<f64>| {
+            (0..size)
+                .map(|j| (0..size).map(|i| matrix[[i, j]].abs()).sum::<TNumber>())
+                .fold(0., TNumber::max)
+        };
+        let condition = norm(&matrix) * norm(&inverse);
+        if condition.is_finite() {
+            condition
+        } else {
+            TNumber::INFINITY
+        }
+    }
+
+    /// Same as [`Polynomial::new_minimized`] on moments collected beforehand
+    pub fn from_moments(moments: &Moments, degree: usize) -> Result<Self, ApproximationError> {
+        let size = degree + 1;
+        if moments.count < size {
+            return Err(ApproximationError::NotEnoughPoints {
+                family: polynomial_family(degree),
+                required: size,
+                got: moments.count,
+            });
+        }
+
+        let (rows, rhs) = moments.normal_equations(degree);
+        let mut matrix = General::<f64>::zero(size, size);
+        let mut vector = Vector::<f64>::zero(size);
+        for i in 0..size {
+            for j in 0..size {
+                matrix[[i, j]] = rows[i][j];
+            }
+            vector[i] = rhs[i];
+        }
+
+        let solution = matrix
+            .solve(&vector)
+            .map_err(|_| ApproximationError::SingularMatrix {
+                family: polynomial_family(degree),
+            })?;
+        let coeffs: Vec<_> = (0..size).map(|i| solution[i]).collect();
+        if !coeffs.iter().all(|coeff| coeff.is_finite()) {
+            return Err(ApproximationError::SingularMatrix {
+                family: polynomial_family(degree),
+            });
+        }
+
+        Ok(Polynomial { coeffs })
+    }
+
+    /// Same as [`Polynomial::new_minimized`], but x is mapped to `[-1, 1]` before
+    /// assembling the normal equations and coefficients are converted back afterwards.
+    ///
+    /// Far better conditioned when x span is small compared to its magnitude,
+    /// e.g. `x ∈ [1000, 2000]`
+    pub fn new_minimized_scaled(
+        points: &[Point],
+        degree: usize,
+    ) -> Result<Self, ApproximationError> {
+        let (scaled, basis) = ScaledBasis::new(points);
+        Polynomial::new_minimized(&scaled, degree).and_then(|polynomial| basis.unscale(polynomial))
+    }
+}
+
+/// Affine map of x onto `[-1, 1]`, see [`Polynomial::new_minimized_scaled`]
+#[derive(Clone, Copy, Debug)]
+struct ScaledBasis {
+    center: TNumber,
+    half_span: TNumber,
+}
+
+impl ScaledBasis {
+    /// Basis spanning the x of the points and the points mapped into it
+    fn new(points: &[Point]) -> (Vec<Point>, Self) {
+        let (min, max) = points.iter().fold(
+            (TNumber::INFINITY, TNumber::NEG_INFINITY),
+            |(min, max), point| (min.min(point.x), max.max(point.x)),
+        );
+        let center = (min + max) / 2.;
+        let half_span = if max - min > 0. { (max - min) / 2. } else { 1. };
+
+        let scaled: Vec<_> = points
+            .iter()
+            .map(|&Point { x, y, w }| Point {
+                x: (x - center) / half_span,
+                y,
+                w,
+            })
+            .collect();
+        (scaled, ScaledBasis { center, half_span })
+    }
+
+    /// Polynomial fitted over the scaled x expressed in the original x
+    fn unscale(self, polynomial: Polynomial) -> Result<Polynomial, ApproximationError> {
+        let ScaledBasis { center, half_span } = self;
+        let scaled = polynomial.coeffs;
+        let degree = scaled.len() - 1;
+
+        // sum of b_k * ((x - center) / half_span)^k expanded with binomial theorem
+        let mut coeffs = vec![0.; degree + 1];
+        for (k, b) in scaled.iter().enumerate() {
+            let scale = b / half_span.powi(k as i32);
+            let mut binomial = 1.;
+            for (j, coeff) in coeffs.iter_mut().enumerate().take(k + 1) {
+                *coeff += scale * binomial * (-center).powi((k - j) as i32);
+                binomial = binomial * (k - j) as TNumber / (j + 1) as TNumber;
+            }
+        }
+        if !coeffs.iter().all(|coeff| coeff.is_finite()) {
+            return Err(ApproximationError::SingularMatrix {
+                family: polynomial_family(degree),
+            });
+        }
+
+        Ok(Polynomial { coeffs })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Exponent {
+    a0: TNumber,
+    a1: TNumber,
+}
+
+impl Display for Exponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for Exponent {
+    fn name(&self) -> &'static str {
+        "Exponent"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        (self.a0 * x + self.a1).exp()
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Exponent {
+            a0: self.a0,
+            a1: self.a1,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::Exponent.template(), &[self.a0, self.a1], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        // a flat exponent has no `1 / a0` antiderivative
+        if self.a0 == 0. {
+            return self.a1.exp() * (range.end - range.start);
+        }
+        (self.compute(range.end) - self.compute(range.start)) / self.a0
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.a0 * self.compute(x)
+    }
+}
+
+impl MinimizedFunction for Exponent {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        let points: Vec<_> = points
+            .iter()
+            .map(|&Point { x, y, w }| Point { x, y: y.ln(), w })
+            .collect();
+
+        let Linear { a: a0, b: a1 } = minimized_as("Exponent", &points)?;
+        let exponent = Exponent { a0, a1 };
+
+        // e^(a0*x + a1) easily reaches infinity for large x
+        if !points
+            .iter()
+            .all(|point| exponent.compute(point.x).is_finite())
+        {
+            return Err(ApproximationError::Overflow {
+                family: "Exponent".to_owned(),
+            });
+        }
+
+        Ok(exponent)
+    }
+
+    fn supports(points: &[Point]) -> bool {
+        points.iter().all(|point| point.y > 0.)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Logrithm {
+    a0: TNumber,
+    a1: TNumber,
+}
+
+impl Display for Logrithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for Logrithm {
+    fn name(&self) -> &'static str {
+        "Natural Logarithm"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a0 * x.ln() + self.a1
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Logarithm {
+            a0: self.a0,
+            a1: self.a1,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::Logarithm.template(), &[self.a0, self.a1], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.a0 / x
+    }
+}
+
+impl MinimizedFunction for Logrithm {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        let points_mapped: Vec<_> = points
+            .iter()
+            .map(|&Point { x, y, w }| Point { x: x.ln(), y, w })
+            .collect();
+
+        let Linear { a: a0, b: a1 } = minimized_as("Natural Logarithm", &points_mapped)?;
+
+        Ok(Logrithm { a0, a1 })
+    }
+
+    fn supports(points: &[Point]) -> bool {
+        points.iter().all(|point| point.x > 0.)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LogarithmBase10 {
+    a0: TNumber,
+    a1: TNumber,
+}
+
+impl Display for LogarithmBase10 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for LogarithmBase10 {
+    fn name(&self) -> &'static str {
+        "Base 10 Logarithm"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a0 * x.log10() + self.a1
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Log10 {
+            a0: self.a0,
+            a1: self.a1,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::Log10.template(), &[self.a0, self.a1], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.a0 / (x * std::f64::consts::LN_10)
+    }
+}
+
+impl MinimizedFunction for LogarithmBase10 {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        let points_mapped: Vec<_> = points
+            .iter()
+            .map(|&Point { x, y, w }| Point { x: x.log10(), y, w })
+            .collect();
+
+        let Linear { a: a0, b: a1 } = minimized_as("Base 10 Logarithm", &points_mapped)?;
+
+        Ok(LogarithmBase10 { a0, a1 })
+    }
+
+    fn supports(points: &[Point]) -> bool {
+        points.iter().all(|point| point.x > 0.)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Power {
+    a0: TNumber,
+    a1: TNumber,
+}
+
+impl Display for Power {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for Power {
+    fn name(&self) -> &'static str {
+        "Power"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a0.exp() * x.powf(self.a1)
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Power {
+            a0: self.a0,
+            a1: self.a1,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::Power.template(), &[self.a0.exp(), self.a1], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.a0.exp() * self.a1 * x.powf(self.a1 - 1.)
+    }
+}
+
+impl MinimizedFunction for Power {
+    /// Unlike other logarithm based families reports the points out of domain
+    /// instead of being silently skipped
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        let mut invalid = Vec::new();
+        let mut points_mapped = Vec::with_capacity(points.len());
+        for (index, &Point { x, y, w }) in points.iter().enumerate() {
+            if x > 0. && y > 0. {
+                points_mapped.push(Point {
+                    x: x.ln(),
+                    y: y.ln(),
+                    w,
+                });
+            } else {
+                invalid.push(index + 1);
+            }
+        }
+
+        if !invalid.is_empty() {
+            return Err(ApproximationError::NonPositivePoints {
+                family: "Power".to_owned(),
+                indices: invalid,
+            });
+        }
+
+        // ln(y) = ln(c) + k*ln(x), so intercept is a0 and slope is power
+        let Linear { a: a1, b: a0 } = minimized_as("Power", &points_mapped)?;
+
+        Ok(Power { a0, a1 })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Sinusoidal {
+    amplitude: TNumber,
+    frequency: TNumber,
+    phase: TNumber,
+    offset: TNumber,
+}
+
+/// Amount of candidate frequencies tried by [`Sinusoidal`]
+const SINUSOIDAL_FREQUENCIES: usize = 100;
+
+impl Display for Sinusoidal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for Sinusoidal {
+    fn name(&self) -> &'static str {
+        "Sinusoidal"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.amplitude * (self.frequency * x + self.phase).sin() + self.offset
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Sinusoidal {
+            amplitude: self.amplitude,
+            frequency: self.frequency,
+            phase: self.phase,
+            offset: self.offset,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(
+            Family::Sinusoidal.template(),
+            &[self.amplitude, self.frequency, self.phase, self.offset],
+            style,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.amplitude * self.frequency * (self.frequency * x + self.phase).cos()
+    }
+}
+
+impl Sinusoidal {
+    /// Solves for amplitude, phase and offset with fixed frequency.
+    ///
+    /// `A*sin(w*x + p) + C = a*sin(w*x) + b*cos(w*x) + C`
+    /// is linear in `a`, `b` and `C`.
+    fn new_with_frequency(points: &[Point], frequency: TNumber) -> Option<Self> {
+        let mut matrix = General::<f64>::zero(3, 3);
+        let mut vector = Vector::<f64>::zero(3);
+
+        points.iter().for_each(|&Point { x, y, w }| {
+            let basis = [(frequency * x).sin(), (frequency * x).cos(), 1.];
+            for i in 0..3 {
+                for j in 0..3 {
+                    matrix[[i, j]] += w * basis[i] * basis[j];
+                }
+                vector[i] += w * basis[i] * y;
+            }
+        });
+
+        let coeffs = matrix.solve(&vector).ok()?;
+        let (a, b, offset) = (coeffs[0], coeffs[1], coeffs[2]);
+
+        Some(Sinusoidal {
+            amplitude: a.hypot(b),
+            frequency,
+            phase: b.atan2(a),
+            offset,
+        })
+    }
+}
+
+impl MinimizedFunction for Sinusoidal {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        require_points("Sinusoidal", points, 4)?;
+
+        let (min, max) = points.iter().fold(
+            (TNumber::INFINITY, TNumber::NEG_INFINITY),
+            |(min, max), point| (min.min(point.x), max.max(point.x)),
+        );
+        let span = max - min;
+
+        let squared_error = |function: &Sinusoidal| {
+            points
+                .iter()
+                .map(|&Point { x, y, w }| w * (function.compute(x) - y).powi(2))
+                .sum::<TNumber>()
+        };
+        let best_of = |frequencies: &mut dyn Iterator<Item = TNumber>| {
+            frequencies
+                .filter_map(|frequency| Sinusoidal::new_with_frequency(points, frequency))
+                .min_by(|a, b| squared_error(a).total_cmp(&squared_error(b)))
+        };
+
+        // candidates range from a quarter of a period
+        // up to SINUSOIDAL_FREQUENCIES / 4 periods over the x span,
+        // but no more than (n - 1) / 2 periods: higher frequencies just alias the points
+        let step = std::f64::consts::PI / (2. * span);
+        let candidates = SINUSOIDAL_FREQUENCIES.min(2 * (points.len() - 1));
+        let coarse = best_of(&mut (1..=candidates).map(|k| k as TNumber * step));
+
+        // then refine around the best candidate with the same amount of steps.
+        // Refined grid contains the coarse frequency, so it can only improve
+        coarse
+            .map(|coarse| {
+                best_of(&mut (0..=SINUSOIDAL_FREQUENCIES).map(|k| {
+                    coarse.frequency - step
+                        + 2. * step * k as TNumber / SINUSOIDAL_FREQUENCIES as TNumber
+                }))
+                .unwrap_or(coarse)
+            })
+            .ok_or(ApproximationError::SingularMatrix {
+                family: "Sinusoidal".to_owned(),
+            })
+    }
+}
+
+/// Saturating curve, levels off at `offset ± amplitude*π/2` on both ends
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ArcTan {
+    scale: TNumber,
+    shift: TNumber,
+    amplitude: TNumber,
+    offset: TNumber,
+}
+
+/// Amount of candidate scales and centers, each, tried by [`ArcTan`]
+const ARCTAN_STEPS: usize = 20;
+/// Passes of the finer grid search around the best candidate
+const ARCTAN_REFINEMENTS: usize = 2;
+/// Candidate `scale * x span` range, from almost a line to almost a step
+const ARCTAN_SCALES: Range<TNumber> = 0.5..200.;
+
+impl Display for ArcTan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for ArcTan {
+    fn name(&self) -> &'static str {
+        "Arctangent"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.amplitude * (self.scale * x + self.shift).atan() + self.offset
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::ArcTan {
+            scale: self.scale,
+            shift: self.shift,
+            amplitude: self.amplitude,
+            offset: self.offset,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(
+            Family::ArcTan.template(),
+            &[self.amplitude, self.scale, self.shift, self.offset],
+            style,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.amplitude * self.scale / (1. + (self.scale * x + self.shift).powi(2))
+    }
+}
+
+impl ArcTan {
+    /// Solves for amplitude and offset with fixed scale and shift,
+    /// `A*atan(s*x + h) + C` is linear in `A` and `C`
+    fn new_with_scale_shift(points: &[Point], scale: TNumber, shift: TNumber) -> Option<Self> {
+        let mut matrix = General::<f64>::zero(2, 2);
+        let mut vector = Vector::<f64>::zero(2);
+
+        points.iter().for_each(|&Point { x, y, w }| {
+            let basis = [(scale * x + shift).atan(), 1.];
+            for i in 0..2 {
+                for j in 0..2 {
+                    matrix[[i, j]] += w * basis[i] * basis[j];
+                }
+                vector[i] += w * basis[i] * y;
+            }
+        });
+
+        let coeffs = matrix.solve(&vector).ok()?;
+        Some(ArcTan {
+            scale,
+            shift,
+            amplitude: coeffs[0],
+            offset: coeffs[1],
+        })
+    }
+}
+
+impl MinimizedFunction for ArcTan {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        require_points("Arctangent", points, 4)?;
+
+        let (min, max) = points.iter().fold(
+            (TNumber::INFINITY, TNumber::NEG_INFINITY),
+            |(min, max), point| (min.min(point.x), max.max(point.x)),
+        );
+        let span = max - min;
+
+        let squared_error = |function: &ArcTan| {
+            points
+                .iter()
+                .map(|&Point { x, y, w }| w * (function.compute(x) - y).powi(2))
+                .sum::<TNumber>()
+        };
+        // the curve is searched as `atan(scale*(x - center))`, negative scales
+        // are covered by the sign of the amplitude
+        // the error is computed once per candidate, the grid is large
+        let best_of = |grid: &mut dyn Iterator<Item = (TNumber, TNumber)>| {
+            grid.filter_map(|(log_scale, center)| {
+                let scale = log_scale.exp();
+                ArcTan::new_with_scale_shift(points, scale, -scale * center)
+            })
+            .map(|function| (squared_error(&function), function))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        };
+        let grid = |log_scales: Range<TNumber>, centers: Range<TNumber>| {
+            let last = (ARCTAN_STEPS - 1) as TNumber;
+            (0..ARCTAN_STEPS).flat_map(move |i| {
+                let log_scale =
+                    log_scales.start + (log_scales.end - log_scales.start) * i as TNumber / last;
+                let centers = centers.clone();
+                (0..ARCTAN_STEPS).map(move |j| {
+                    let center =
+                        centers.start + (centers.end - centers.start) * j as TNumber / last;
+                    (log_scale, center)
+                })
+            })
+        };
+
+        // scales are spread evenly in log space, centers over the x span
+        let log_scales = (ARCTAN_SCALES.start / span).ln()..(ARCTAN_SCALES.end / span).ln();
+        let mut log_step = (log_scales.end - log_scales.start) / (ARCTAN_STEPS - 1) as TNumber;
+        let mut center_step = span / (ARCTAN_STEPS - 1) as TNumber;
+        let (mut best_error, mut best) =
+            best_of(&mut grid(log_scales, min..max)).ok_or(ApproximationError::SingularMatrix {
+                family: "Arctangent".to_owned(),
+            })?;
+
+        // then refine around the best candidate within one step of the previous grid,
+        // a refined candidate is only taken when it is better
+        for _ in 0..ARCTAN_REFINEMENTS {
+            let log_scale = best.scale.ln();
+            let center = -best.shift / best.scale;
+            if let Some((error, refined)) = best_of(&mut grid(
+                (log_scale - log_step)..(log_scale + log_step),
+                (center - center_step)..(center + center_step),
+            )) {
+                if error < best_error {
+                    (best_error, best) = (error, refined);
+                }
+            }
+            log_step *= 2. / (ARCTAN_STEPS - 1) as TNumber;
+            center_step *= 2. / (ARCTAN_STEPS - 1) as TNumber;
+        }
+
+        Ok(best)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Hyperbolic {
+    a: TNumber,
+    b: TNumber,
+}
+
+impl Display for Hyperbolic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for Hyperbolic {
+    fn name(&self) -> &'static str {
+        "Hyperbolic"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a / x + self.b
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Hyperbolic {
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::Hyperbolic.template(), &[self.a, self.b], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        -self.a / x.powi(2)
+    }
+}
+
+impl MinimizedFunction for Hyperbolic {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        let points_mapped: Vec<_> = points
+            .iter()
+            .map(|&Point { x, y, w }| Point { x: 1. / x, y, w })
+            .collect();
+
+        let Linear { a, b } = minimized_as("Hyperbolic", &points_mapped)?;
+
+        Ok(Hyperbolic { a, b })
+    }
+
+    fn supports(points: &[Point]) -> bool {
+        points.iter().all(|point| point.x != 0.)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SquareRoot {
+    a: TNumber,
+    b: TNumber,
+}
+
+impl Display for SquareRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for SquareRoot {
+    fn name(&self) -> &'static str {
+        "Square Root"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a * x.sqrt() + self.b
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::SquareRoot {
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::SquareRoot.template(), &[self.a, self.b], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        self.a / (2. * x.sqrt())
+    }
+}
+
+impl MinimizedFunction for SquareRoot {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        let points_mapped: Vec<_> = points
+            .iter()
+            .map(|&Point { x, y, w }| Point { x: x.sqrt(), y, w })
+            .collect();
+
+        let Linear { a, b } = minimized_as("Square Root", &points_mapped)?;
+
+        Ok(SquareRoot { a, b })
+    }
+
+    fn supports(points: &[Point]) -> bool {
+        points.iter().all(|point| point.x >= 0.)
+    }
+}
+
+/// Line through the origin, for data where the intercept is known to be zero
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LinearNoIntercept {
+    a: TNumber,
+}
+
+impl Display for LinearNoIntercept {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for LinearNoIntercept {
+    fn name(&self) -> &'static str {
+        "Linear without intercept"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a * x
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::LinearNoIntercept { a: self.a }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::LinearNoIntercept.template(), &[self.a], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        polynomial_integral(&[0., self.a], range)
+    }
+
+    fn derivative(&self, _x: TNumber) -> TNumber {
+        self.a
+    }
+}
+
+impl MinimizedFunction for LinearNoIntercept {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        require_points("Linear without intercept", points, 1)?;
+
+        let (sxy, sxx) = points
+            .iter()
+            .fold((0., 0.), |(sxy, sxx), Point { x, y, w }| {
+                (sxy + w * x * y, sxx + w * x.powi(2))
+            });
+        if sxx == 0. {
+            return Err(ApproximationError::SingularMatrix {
+                family: "Linear without intercept".to_owned(),
+            });
+        }
+
+        Ok(LinearNoIntercept { a: sxy / sxx })
+    }
+}
+
+/// Pairwise slopes of [`RobustLinear`] grow quadratically, ~12.5 million for this many points
+pub const ROBUST_LINEAR_MAX_POINTS: usize = 5000;
+
+/// Line fitted with Theil–Sen estimator: slope is the median of slopes between every
+/// pair of points and addition is the median of `y - a*x`.
+///
+/// A few spikes barely move it, unlike least squares. Weights are ignored.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RobustLinear {
+    a: TNumber,
+    b: TNumber,
+}
+
+impl Display for RobustLinear {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for RobustLinear {
+    fn name(&self) -> &'static str {
+        "Robust Linear"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.a * x + self.b
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::RobustLinear {
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(Family::RobustLinear.template(), &[self.a, self.b], style)
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn integrate(&self, range: Range<TNumber>) -> TNumber {
+        polynomial_integral(&[self.b, self.a], range)
+    }
+
+    fn derivative(&self, _x: TNumber) -> TNumber {
+        self.a
+    }
+}
+
+impl MinimizedFunction for RobustLinear {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        require_points("Robust Linear", points, 2)?;
+        if points.len() > ROBUST_LINEAR_MAX_POINTS {
+            return Err(ApproximationError::TooManyPoints {
+                family: "Robust Linear".to_owned(),
+                limit: ROBUST_LINEAR_MAX_POINTS,
+                got: points.len(),
+            });
+        }
+
+        // O(n^2) pairs, hence the limit above
+        let slopes: Vec<_> = points
+            .iter()
+            .enumerate()
+            .flat_map(|(i, first)| {
+                points[i + 1..]
+                    .iter()
+                    .filter(move |second| second.x != first.x)
+                    .map(move |second| (second.y - first.y) / (second.x - first.x))
+            })
+            .collect();
+        // every x is the same, slope is undefined
+        let a = median(slopes).ok_or_else(|| ApproximationError::SingularMatrix {
+            family: "Robust Linear".to_owned(),
+        })?;
+        let b = median(points.iter().map(|point| point.y - a * point.x).collect())
+            .expect("there are at least 2 points");
+
+        Ok(RobustLinear { a, b })
+    }
+}
+
+/// Middle value, mean of the two middle ones for even length. `None` when empty
+fn median(mut values: Vec<TNumber>) -> Option<TNumber> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.
+    } else {
+        values[middle]
+    })
+}
+
+/// Bell curve `amplitude*e^(-(x - mean)^2 / (2*sigma^2))`.
+///
+/// `ln(y)` of it is a quadratic of x, which is fitted instead, so y must be positive
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Gaussian {
+    amplitude: TNumber,
+    mean: TNumber,
+    sigma: TNumber,
+}
+
+impl Display for Gaussian {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for Gaussian {
+    fn name(&self) -> &'static str {
+        "Gaussian"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        self.amplitude * (-(x - self.mean).powi(2) / (2. * self.sigma.powi(2))).exp()
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::Gaussian {
+            amplitude: self.amplitude,
+            mean: self.mean,
+            sigma: self.sigma,
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        fill_template(
+            Family::Gaussian.template(),
+            &[self.amplitude, self.mean, self.sigma],
+            style,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, x: TNumber) -> TNumber {
+        -(x - self.mean) / self.sigma.powi(2) * self.compute(x)
+    }
+}
+
+impl MinimizedFunction for Gaussian {
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        let invalid: Vec<_> = (1..=points.len())
+            .filter(|&number| points[number - 1].y <= 0.)
+            .collect();
+        if !invalid.is_empty() {
+            return Err(ApproximationError::NonPositivePoints {
+                family: "Gaussian".to_owned(),
+                indices: invalid,
+            });
+        }
+
+        let log_points: Vec<_> = points
+            .iter()
+            .map(|&Point { x, y, w }| Point { x, y: y.ln(), w })
+            .collect();
+        let coeffs = Polynomial::new_minimized(&log_points, 2)
+            .map_err(|error| match error {
+                ApproximationError::SingularMatrix { .. } => ApproximationError::SingularMatrix {
+                    family: "Gaussian".to_owned(),
+                },
+                ApproximationError::NotEnoughPoints { required, got, .. } => {
+                    ApproximationError::NotEnoughPoints {
+                        family: "Gaussian".to_owned(),
+                        required,
+                        got,
+                    }
+                }
+                error => error,
+            })?
+            .coeffs;
+
+        // ln(y) = ln(amplitude) - (x - mean)^2 / (2*sigma^2) = c0 + c1*x + c2*x^2
+        let [c0, c1, c2] = coeffs[..] else {
+            unreachable!("quadratic has 3 coefficients")
+        };
+        if c2 >= 0. {
+            return Err(ApproximationError::NotBellShaped {
+                family: "Gaussian".to_owned(),
+            });
+        }
+        let variance = -1. / (2. * c2);
+        let mean = c1 * variance;
+
+        Ok(Gaussian {
+            amplitude: (c0 + mean.powi(2) / (2. * variance)).exp(),
+            mean,
+            sigma: variance.sqrt(),
+        })
+    }
+}
+
+/// Connect-the-dots interpolation through the points, not a fit.
+///
+/// Between points values are interpolated linearly, outside of them the end
+/// segments are extended. Points with equal x are merged into their mean y.
+/// It is not one of the [`Family`] candidates, it would always win with zero deviation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "Knots")]
+pub struct PiecewiseLinear {
+    /// Sorted by x, x are distinct
+    knots: Vec<(TNumber, TNumber)>,
+}
+
+/// Serialized [`PiecewiseLinear`] before its knots are checked
+#[derive(Deserialize)]
+struct Knots {
+    knots: Vec<(TNumber, TNumber)>,
+}
+
+impl TryFrom<Knots> for PiecewiseLinear {
+    type Error = ApproximationError;
+
+    fn try_from(Knots { knots }: Knots) -> Result<Self, Self::Error> {
+        PiecewiseLinear::new(knots)
+    }
+}
+
+impl PiecewiseLinear {
+    /// Sorts the knots by x, knots sharing x are merged into their mean y.
+    ///
+    /// Fails when there are no knots
+    pub fn new(mut knots: Vec<(TNumber, TNumber)>) -> Result<Self, ApproximationError> {
+        if knots.is_empty() {
+            return Err(ApproximationError::NotEnoughPoints {
+                family: "Piecewise Linear".to_owned(),
+                required: 1,
+                got: 0,
+            });
+        }
+
+        knots.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged_knots: Vec<(TNumber, TNumber)> = Vec::with_capacity(knots.len());
+        let mut merged = 0;
+        for (knot_x, knot_y) in knots {
+            match merged_knots.last_mut() {
+                // running mean of knots sharing x
+                Some((x, y)) if *x == knot_x => {
+                    merged += 1;
+                    *y += (knot_y - *y) / (merged + 1) as TNumber;
+                }
+                _ => {
+                    merged = 0;
+                    merged_knots.push((knot_x, knot_y));
+                }
+            }
+        }
+
+        Ok(PiecewiseLinear {
+            knots: merged_knots,
+        })
+    }
+}
+
+impl Display for PiecewiseLinear {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_function(self, f)
+    }
+}
+
+impl Function for PiecewiseLinear {
+    fn name(&self) -> &'static str {
+        "Piecewise Linear"
+    }
+
+    fn compute(&self, x: TNumber) -> TNumber {
+        if let [(_, y)] = self.knots[..] {
+            return y;
+        }
+
+        // first segment whose end is at or after x, end segments outside of the range
+        let end = self
+            .knots
+            .partition_point(|&(knot, _)| knot < x)
+            .clamp(1, self.knots.len() - 1);
+        let ((x0, y0), (x1, y1)) = (self.knots[end - 1], self.knots[end]);
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+
+    fn coefficients(&self) -> Coefficients {
+        Coefficients::PiecewiseLinear {
+            knots: self.knots.clone(),
+        }
+    }
+
+    fn formula(&self, style: NumberStyle) -> String {
+        let (first, last) = (self.knots[0].0, self.knots[self.knots.len() - 1].0);
+        format!(
+            "{} segments from x = {} to x = {}",
+            self.knots.len() - 1,
+            number(first, style),
+            number(last, style)
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Function> {
+        Box::new(self.clone())
+    }
+}
+
+impl MinimizedFunction for PiecewiseLinear {
+    /// Only sorts and stores the points, see [`PiecewiseLinear::new`]
+    fn new_minimized(points: &[Point]) -> Result<Self, ApproximationError> {
+        PiecewiseLinear::new(points.iter().map(|point| (point.x, point.y)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    /// Fails with both values when they differ by more than `eps`
+    fn assert_close(a: TNumber, b: TNumber, eps: TNumber) {
+        assert!(
+            (a - b).abs() <= eps,
+            "{} and {} differ by more than {}",
+            a,
+            b,
+            eps
+        );
+    }
+
+    /// Same layout as the files in `fixtures/`
+    #[derive(Deserialize)]
+    struct Fixture {
+        points: Vec<Point>,
+    }
+
+    fn fixture(json: &str) -> Vec<Point> {
+        serde_json::from_str::<Fixture>(json)
+            .expect("fixture is valid")
+            .points
+    }
+
+    /// Points of `function` at `x = 1, 2, ..., count`
+    fn exact(count: usize, function: impl Fn(TNumber) -> TNumber) -> Vec<Point> {
+        (1..=count)
+            .map(|i| {
+                let x = i as TNumber;
+                Point {
+                    x,
+                    y: function(x),
+                    w: 1.,
+                }
+            })
+            .collect()
+    }
+
+    fn points(coordinates: &[(TNumber, TNumber)]) -> Vec<Point> {
+        coordinates
+            .iter()
+            .map(|&(x, y)| Point { x, y, w: 1. })
+            .collect()
+    }
+
+    #[test]
+    fn pearson_correlation_matches_hand_computed() {
+        // n*Σxy - Σx*Σy = 3*25 - 6*11 = 9,
+        // (n*Σx² - (Σx)²)*(n*Σy² - (Σy)²) = (42 - 36)*(135 - 121) = 84
+        let r = pearson_correlation(&points(&[(1., 2.), (2., 4.), (3., 5.)]));
+        assert_close(r, 9. / TNumber::sqrt(84.), 1e-12);
+    }
+
+    #[test]
+    fn pearson_correlation_is_nan_when_all_x_are_equal() {
+        assert!(pearson_correlation(&points(&[(1., 2.), (1., 4.), (1., 5.)])).is_nan());
+    }
+
+    #[test]
+    fn linear_recovers_fixture() {
+        let Linear { a, b } =
+            Linear::new_minimized(&fixture(include_str!("../fixtures/linear.json"))).unwrap();
+        assert_close(a, 2., 1e-12);
+        assert_close(b, 1., 1e-12);
+    }
+
+    #[test]
+    fn quadratic_recovers_fixture() {
+        let Quadratic { a0, a1, a2 } =
+            Quadratic::new_minimized(&fixture(include_str!("../fixtures/quadratic.json"))).unwrap();
+        assert_close(a0, 1., 1e-10);
+        assert_close(a1, -2., 1e-10);
+        assert_close(a2, 0.5, 1e-10);
+    }
+
+    #[test]
+    fn cubic_recovers_fixture() {
+        let Cubic { a0, a1, a2, a3 } =
+            Cubic::new_minimized(&fixture(include_str!("../fixtures/cubic.json"))).unwrap();
+        assert_close(a0, 1., 1e-9);
+        assert_close(a1, 1., 1e-9);
+        assert_close(a2, -1., 1e-9);
+        assert_close(a3, 0.25, 1e-9);
+    }
+
+    /// Weighted, not exactly polynomial points, so every coefficient matters
+    fn noisy() -> Vec<Point> {
+        (0..12)
+            .map(|i| {
+                let x = i as TNumber * 0.7 - 2.;
+                Point {
+                    x,
+                    y: (1.3 * x).sin() + 0.1 * x.powi(3),
+                    w: 1. + (i % 3) as TNumber,
+                }
+            })
+            .collect()
+    }
+
+    /// Normal equations assembled entry by entry, the way they were before [`Moments`]
+    fn hand_assembled(points: &[Point], degree: usize) -> Vec<TNumber> {
+        let size = degree + 1;
+        let mut matrix = General::<f64>::zero(size, size);
+        let mut vector = Vector::<f64>::zero(size);
+        for &Point { x, y, w } in points {
+            for i in 0..size {
+                for j in 0..size {
+                    matrix[[i, j]] += w * x.powi((i + j) as i32);
+                }
+                vector[i] += w * x.powi(i as i32) * y;
+            }
+        }
+        let solution = matrix.solve(&vector).unwrap();
+        (0..size).map(|i| solution[i]).collect()
+    }
+
+    fn assert_same_line(points: &[Point]) {
+        let mut accumulator = LinearAccumulator::default();
+        for &point in points {
+            accumulator.push(point);
+        }
+        let Linear { a, b } = accumulator.finish().unwrap();
+
+        let batch = Linear::new_minimized(points).unwrap();
+        assert_close(a, batch.a, 1e-12);
+        assert_close(b, batch.b, 1e-12);
+        // solved without the accumulator, from the normal equations
+        let Coefficients::Polynomial { coeffs } =
+            Polynomial::new_minimized(points, 1).unwrap().coefficients()
+        else {
+            unreachable!("polynomial has polynomial coefficients")
+        };
+        assert_close(a, coeffs[1], 1e-10);
+        assert_close(b, coeffs[0], 1e-10);
+    }
+
+    #[test]
+    fn linear_accumulator_matches_batch_fit() {
+        let points: Vec<_> = noisy()
+            .into_iter()
+            .map(|point| Point { w: 1., ..point })
+            .collect();
+        assert_same_line(&points);
+    }
+
+    #[test]
+    fn linear_accumulator_matches_weighted_batch_fit() {
+        let points = noisy();
+        assert!(points.iter().any(|point| point.w != 1.));
+        assert_same_line(&points);
+    }
+
+    #[test]
+    fn linear_accumulator_needs_two_points() {
+        let mut accumulator = LinearAccumulator::default();
+        accumulator.push(Point {
+            x: 1.,
+            y: 2.,
+            w: 1.,
+        });
+        assert!(matches!(
+            accumulator.finish(),
+            Err(ApproximationError::NotEnoughPoints { got: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn scaled_basis_is_more_accurate_far_from_zero() {
+        let truth = [5., 0.5, -2e-4, 3e-8];
+        let points: Vec<_> = (0..=40)
+            .map(|i| {
+                let x = 1000. + 25. * i as TNumber;
+                let y = truth.iter().rev().fold(0., |sum, coeff| sum * x + coeff);
+                Point { x, y, w: 1. }
+            })
+            .collect();
+        let relative_errors = |coeffs: Vec<TNumber>| -> Vec<TNumber> {
+            coeffs
+                .iter()
+                .zip(truth)
+                .map(|(coeff, truth)| ((coeff - truth) / truth).abs())
+                .collect()
+        };
+
+        let raw = relative_errors(Polynomial::new_minimized(&points, 3).unwrap().coeffs);
+        let scaled = relative_errors(Polynomial::new_minimized_scaled(&points, 3).unwrap().coeffs);
+        for (raw, scaled) in raw.into_iter().zip(scaled) {
+            assert!(
+                scaled < 1e-11,
+                "relative error {} of the scaled fit",
+                scaled
+            );
+            assert!(
+                scaled < raw,
+                "scaled {} is not better than raw {}",
+                scaled,
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn unscale_expands_binomials() {
+        // 1 + 2t + 3t^2 + 4t^3 with t = (x - 3) / 2
+        let basis = ScaledBasis {
+            center: 3.,
+            half_span: 2.,
+        };
+        let polynomial = basis
+            .unscale(Polynomial {
+                coeffs: vec![1., 2., 3., 4.],
+            })
+            .unwrap();
+        for (coeff, expected) in polynomial.coeffs.into_iter().zip([-8.75, 10., -3.75, 0.5]) {
+            assert_close(coeff, expected, 1e-12);
+        }
+    }
+
+    #[test]
+    fn polynomial_families_share_one_pass() {
+        let points: Vec<_> = (0..100_000)
+            .map(|i| {
+                let x = i as TNumber / 1000.;
+                Point {
+                    x,
+                    y: 1. + x - 0.01 * x.powi(3),
+                    w: 1.,
+                }
+            })
+            .collect();
+
+        let visited = std::cell::Cell::new(0);
+        let counted = points.iter().inspect(|_| visited.set(visited.get() + 1));
+        let moments = Moments::new(counted, 5);
+        let shared: Vec<_> = (1..=5)
+            .map(|degree| Polynomial::from_moments(&moments, degree).unwrap())
+            .collect();
+        // five fits, a single walk over the points
+        assert_eq!(visited.get(), points.len());
+
+        for (degree, shared) in (1..=5).zip(shared) {
+            let separate = Polynomial::new_minimized(&points, degree).unwrap();
+            for (shared, separate) in shared.coeffs.into_iter().zip(separate.coeffs) {
+                assert_close(shared, separate, 1e-9 * separate.abs().max(1.));
+            }
+        }
+    }
+
+    #[test]
+    fn arctan_recovers_generated_curve() {
+        let points: Vec<_> = (0..=40)
+            .map(|i| {
+                let x = i as TNumber / 10.;
+                Point {
+                    x,
+                    y: 3. * (2. * x - 4.).atan() + 1.,
+                    w: 1.,
+                }
+            })
+            .collect();
+
+        let ArcTan {
+            scale,
+            shift,
+            amplitude,
+            offset,
+        } = ArcTan::new_minimized(&points).unwrap();
+        // a grid search, not an exact solve
+        assert_close(scale, 2., 0.01);
+        assert_close(shift, -4., 0.01);
+        assert_close(amplitude, 3., 0.01);
+        assert_close(offset, 1., 0.01);
+    }
+
+    #[test]
+    fn max_degree_limits_every_polynomial_family() {
+        let points = fixture(include_str!("../fixtures/cubic.json"));
+        let names = |max_degree| -> Vec<String> {
+            let options = FitOptions {
+                max_degree,
+                families: vec![Family::Quadratic, Family::Cubic, Family::Polynomial],
+                ..FitOptions::default()
+            };
+            create_approximations(&points, &options)
+                .iter()
+                .map(|function| function.to_string().lines().next().unwrap().to_owned())
+                .collect()
+        };
+
+        assert!(names(1).is_empty());
+        assert_eq!(names(2), ["Quadratic"]);
+        assert_eq!(names(3), ["Quadratic", "Cubic"]);
+        assert_eq!(
+            names(5),
+            [
+                "Quadratic",
+                "Cubic",
+                "Polynomial of degree 4",
+                "Polynomial of degree 5"
+            ]
+        );
+    }
+
+    #[test]
+    fn moments_match_hand_assembled_equations() {
+        let points = noisy();
+
+        let quadratic = Quadratic::new_minimized(&points).unwrap().coefficients();
+        for (moments, hand) in quadratic
+            .values()
+            .into_iter()
+            .zip(hand_assembled(&points, 2))
+        {
+            assert_close(moments, hand, 1e-10);
+        }
+        let cubic = Cubic::new_minimized(&points).unwrap().coefficients();
+        for (moments, hand) in cubic.values().into_iter().zip(hand_assembled(&points, 3)) {
+            assert_close(moments, hand, 1e-10);
+        }
+    }
+
+    #[test]
+    fn exponent_recovers_coefficients() {
+        let points = exact(8, |x| (0.5 * x - 1.).exp());
+        let Exponent { a0, a1 } = Exponent::new_minimized(&points).unwrap();
+        assert_close(a0, 0.5, 1e-12);
+        assert_close(a1, -1., 1e-12);
+    }
+
+    #[test]
+    fn power_recovers_coefficients() {
+        let points = exact(8, |x| 3. * x.powf(1.5));
+        let power = Power::new_minimized(&points).unwrap();
+        assert_close(power.a0.exp(), 3., 1e-12);
+        assert_close(power.a1, 1.5, 1e-12);
+    }
+
+    #[test]
+    fn power_display_prints_exponentiated_coefficient() {
+        let power = Power::new_minimized(&exact(8, |x| 3. * x.powf(1.5))).unwrap();
+        let printed = power.to_string();
+        let formula = printed.lines().nth(1).unwrap();
+        let (coefficient, exponent) = formula.split_once("*x^").unwrap();
+
+        let coefficient: TNumber = coefficient.parse().unwrap();
+        assert_close(coefficient, power.a0.exp(), 1e-12);
+        assert_close(exponent.parse().unwrap(), power.a1, 1e-12);
+        // the printed equation is the one that is computed
+        assert_close(
+            coefficient * TNumber::powf(2., power.a1),
+            power.compute(2.),
+            1e-9,
+        );
+        assert_close(power.compute(2.), 3. * TNumber::powf(2., 1.5), 1e-9);
+    }
+
+    #[test]
+    fn formula_follows_number_style() {
+        let linear = Linear { a: 2., b: 0.125 };
+        let style = NumberStyle {
+            precision: Some(2),
+            scientific: false,
+        };
+        assert_eq!(linear.formula(style), "2.00*x + 0.12");
+        assert_eq!(linear.formula(NumberStyle::default()), "2*x + 0.125");
+        assert_eq!(format!("{:#.1}", linear), "Linear\n2.0e0*x + 1.2e-1\n");
+    }
+
+    #[test]
+    fn every_family_fills_its_template() {
+        let points = exact(9, |x| 2. * (-(x - 4.5).powi(2) / 8.).exp() + 0.1 * x);
+        let options = FitOptions {
+            max_degree: 4,
+            ..FitOptions::default()
+        };
+        let functions = create_approximations(&points, &options);
+        assert_eq!(functions.len(), Family::ALL.len());
+
+        for function in functions {
+            let formula = function.formula(NumberStyle::default());
+            assert!(!formula.contains(['{', '}']), "unfilled {}", formula);
+            assert_eq!(function.to_string().lines().nth(1), Some(formula.as_str()));
+        }
+    }
+
+    #[test]
+    fn logarithm_recovers_coefficients() {
+        let points = exact(8, |x| 2. * x.ln() + 0.5);
+        let Logrithm { a0, a1 } = Logrithm::new_minimized(&points).unwrap();
+        assert_close(a0, 2., 1e-12);
+        assert_close(a1, 0.5, 1e-12);
+    }
+
+    #[test]
+    fn piecewise_linear_rejects_empty_knots() {
+        let parsed =
+            serde_json::from_str::<AnyFunction>(r#"{"family":"piecewise_linear","knots":[]}"#);
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn piecewise_linear_sorts_and_merges_deserialized_knots() {
+        let parsed: AnyFunction = serde_json::from_str(
+            r#"{"family":"piecewise_linear","knots":[[3,6],[1,2],[3,4],[2,3]]}"#,
+        )
+        .unwrap();
+        let AnyFunction::PiecewiseLinear(piecewise) = parsed else {
+            panic!("expected piecewise linear, got {:?}", parsed);
+        };
+
+        assert_eq!(piecewise.knots, [(1., 2.), (2., 3.), (3., 5.)]);
+        assert_close(piecewise.compute(1.5), 2.5, 1e-12);
+        assert_close(piecewise.compute(2.5), 4., 1e-12);
+    }
+
+    /// Positive, so every family gets to fit
+    fn positive(count: usize) -> Vec<Point> {
+        (1..=count)
+            .map(|i| {
+                let x = i as TNumber * 10. / count as TNumber;
+                Point {
+                    x,
+                    y: 2. + (1.3 * x).sin() + 0.1 * x.powi(2),
+                    w: 1. + (i % 3) as TNumber,
+                }
+            })
+            .collect()
+    }
+
+    /// Each family fitted on its own, nothing is left to run concurrently
+    fn one_by_one(points: &[Point], options: &FitOptions) -> Vec<Approximation> {
+        options
+            .families
+            .iter()
+            .flat_map(|&family| {
+                let options = FitOptions {
+                    families: vec![family],
+                    ..options.clone()
+                };
+                try_create_approximations(points, &options)
+            })
+            .collect()
+    }
+
+    fn ranking(
+        points: &[Point],
+        functions: &[Box<dyn Function>],
+        criterion: Criterion,
+    ) -> Vec<usize> {
+        rank(
+            functions,
+            &FitMetrics::for_all(points, functions),
+            criterion,
+        )
+        .0
+    }
+
+    #[test]
+    fn rank_prefers_fewer_parameters_by_either_criterion() {
+        let points = fixture(include_str!("../fixtures/quadratic.json"));
+        let functions = create_approximations(&points, &FitOptions::default());
+        let metrics = FitMetrics::for_all(&points, &functions);
+
+        for criterion in [Criterion::Rms, Criterion::Aic] {
+            let (ranking, _) = rank(&functions, &metrics, criterion);
+            assert_eq!(functions[ranking[0]].name(), "Quadratic", "{:?}", criterion);
+        }
+    }
+
+    /// Runs with and without the `parallel` feature, both must match the one-by-one order
+    #[test]
+    fn approximations_keep_family_order() {
+        let points = positive(40);
+        let options = FitOptions {
+            max_degree: 5,
+            ..FitOptions::default()
+        };
+
+        let describe = |approximations: Vec<Approximation>| -> Vec<_> {
+            approximations
+                .into_iter()
+                .map(|approximation| {
+                    approximation.map(|function| (function.name(), function.coefficients()))
+                })
+                .map(|approximation| format!("{:?}", approximation))
+                .collect()
+        };
+        let together = describe(try_create_approximations(&points, &options));
+        let separately = describe(one_by_one(&points, &options));
+        assert_eq!(together, separately);
+
+        let together = create_approximations(&points, &options);
+        let separately: Vec<_> = one_by_one(&points, &options)
+            .into_iter()
+            .flatten()
+            .collect();
+        for criterion in [Criterion::Rms, Criterion::Aic] {
+            assert_eq!(
+                ranking(&points, &together, criterion),
+                ranking(&points, &separately, criterion)
+            );
+        }
     }
 }
@@ -0,0 +1,410 @@
+use std::{fs::File, io::Write, ops::Deref, path::Path};
+
+use cli_table::format::{Border, HorizontalLine, Separator, VerticalLine};
+use comp_math_lab4::{
+    methods::{polynomial_family, Coefficients, Family, NumberStyle},
+    FitMetrics, Function, Point, TNumber,
+};
+use serde::Serialize;
+
+use crate::cli::{OutputFormat, RunConfig};
+
+/// [`Function::name`], polynomials also tell their degree so rows stay distinct
+pub fn function_name(function: &dyn Function) -> String {
+    match function.coefficients() {
+        Coefficients::Polynomial { coeffs } => polynomial_family(coeffs.len() - 1),
+        _ => function.name().to_owned(),
+    }
+}
+
+/// Table cell, `scientific` gives `1.2346e3` instead of `1234.5600`
+fn number(value: TNumber, precision: usize, scientific: bool) -> String {
+    if scientific {
+        format!("{:.*e}", precision, value)
+    } else {
+        format!("{:.*}", precision, value)
+    }
+}
+
+/// `ascii` borders are drawn with `+`, `-` and `|` for terminals that mangle box drawing
+fn table_border(ascii: bool) -> Border {
+    if ascii {
+        return Border::builder()
+            .top(HorizontalLine::new('+', '+', '+', '-'))
+            .left(VerticalLine::new('|'))
+            .right(VerticalLine::new('|'))
+            .bottom(HorizontalLine::new('+', '+', '+', '-'))
+            .build();
+    }
+
+    Border::builder()
+        .top(HorizontalLine::new('╭', '╮', '┬', '─'))
+        .left(VerticalLine::new('│'))
+        .right(VerticalLine::new('│'))
+        .bottom(HorizontalLine::new('╰', '╯', '┴', '─'))
+        .build()
+}
+
+fn table_separator(ascii: bool) -> Separator {
+    let row = if ascii {
+        HorizontalLine::new('+', '+', '+', '-')
+    } else {
+        HorizontalLine::new('├', '┤', '┼', '─')
+    };
+    Separator::builder().row(Some(row)).build()
+}
+
+/// Every candidate with its metrics, including how many points it was measured on
+/// so families working on different subsets are not compared blindly
+pub fn print_summary(
+    functions: &[Box<dyn Function>],
+    metrics: &[FitMetrics],
+    config: &RunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use cli_table::Table;
+    let &RunConfig {
+        precision,
+        scientific,
+        ascii,
+        ..
+    } = config;
+    let table = functions
+        .iter()
+        .zip(metrics)
+        .map(|(function, metrics)| {
+            vec![
+                function_name(function.deref()),
+                number(metrics.standard_deviation, precision, scientific),
+                number(metrics.r_squared, precision, scientific),
+                metrics.deviations.len().to_string(),
+            ]
+        })
+        .table()
+        .border(table_border(ascii))
+        .separator(table_separator(ascii))
+        .title(["Function", "Standard deviation", "R²", "Fit on N points"])
+        .display()?;
+
+    println!("{table}");
+    Ok(())
+}
+
+/// `points` are `(index, row)` pairs where `index` is the position in the input,
+/// so a point keeps its number when the rows are sorted
+pub fn print_points(
+    points: &[(usize, &(Point, TNumber, TNumber))],
+    config: &RunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use cli_table::Table;
+    let &RunConfig {
+        precision,
+        scientific,
+        relative,
+        swap_axes,
+        ascii,
+        ..
+    } = config;
+    let (low, high) = outlier_fences(points.iter().map(|(_, point)| point.2).collect());
+    let largest_y = points.iter().fold(0., |largest: TNumber, (_, point)| {
+        largest.max(point.0.y.abs())
+    });
+    let table = points
+        .iter()
+        .map(|&(index, point)| {
+            let mut row = vec![
+                (index + 1).to_string(),
+                number(point.0.x, precision, scientific),
+                number(point.0.y, precision, scientific),
+                number(point.1, precision, scientific),
+                number(point.2, precision, scientific),
+            ];
+            if relative {
+                // relative error of y ≈ 0 is meaningless, near zero compared to the data
+                row.push(if point.0.y.abs() <= NEAR_ZERO * largest_y {
+                    "—".to_owned()
+                } else {
+                    format!(
+                        "{}%",
+                        number(point.2 / point.0.y * 100., precision, scientific)
+                    )
+                });
+            }
+            row.push(if point.2 < low || point.2 > high {
+                "*".to_owned()
+            } else {
+                String::new()
+            });
+            row
+        })
+        .table()
+        .border(table_border(ascii))
+        .separator(table_separator(ascii))
+        .title(
+            if swap_axes {
+                ["Point number", "Y", "X", "φ(y)", "ε"]
+            } else {
+                ["Point number", "X", "Y", "φ(x)", "ε"]
+            }
+            .into_iter()
+            .chain(relative.then_some(if swap_axes { "ε/x" } else { "ε/y" }))
+            .chain(["Outlier"])
+            .collect::<Vec<_>>(),
+        )
+        .display()?;
+
+    println!("{table}");
+    Ok(())
+}
+
+/// Fraction of the largest `|y|` below which y has no meaningful relative error
+const NEAR_ZERO: TNumber = 1e-9;
+
+/// Residuals outside of `[Q1 - 1.5*IQR, Q3 + 1.5*IQR]` are outliers (Tukey's fences)
+fn outlier_fences(mut epsilons: Vec<TNumber>) -> (TNumber, TNumber) {
+    if epsilons.is_empty() {
+        return (TNumber::NEG_INFINITY, TNumber::INFINITY);
+    }
+
+    epsilons.sort_by(|a, b| a.total_cmp(b));
+    let q1 = quantile(&epsilons, 0.25);
+    let q3 = quantile(&epsilons, 0.75);
+    let iqr = q3 - q1;
+    (q1 - 1.5 * iqr, q3 + 1.5 * iqr)
+}
+
+/// Linear interpolation between closest ranks of sorted values
+fn quantile(sorted: &[TNumber], fraction: TNumber) -> TNumber {
+    let position = fraction * (sorted.len() - 1) as TNumber;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as TNumber)
+}
+
+/// Up to [`RunConfig::top_errors`] points with the largest `|ε|`,
+/// the ones driving the deviation up
+pub fn print_top_errors(points: &[(Point, TNumber, TNumber)], config: &RunConfig) {
+    let &RunConfig {
+        top_errors: count,
+        precision,
+        scientific,
+        swap_axes,
+        ..
+    } = config;
+    if count == 0 {
+        return;
+    }
+
+    let mut ranked: Vec<_> = points.iter().enumerate().collect();
+    ranked.sort_by(|(_, a), (_, b)| b.2.abs().total_cmp(&a.2.abs()));
+
+    // same order and names as the columns of `print_points`
+    if swap_axes {
+        println!("Largest errors, points are (y, x):");
+    } else {
+        println!("Largest errors:");
+    }
+    for (index, (point, _, epsilon)) in ranked.into_iter().take(count) {
+        println!(
+            "  {}: ({}, {}) ε = {}",
+            index + 1,
+            number(point.x, precision, scientific),
+            number(point.y, precision, scientific),
+            number(*epsilon, precision, scientific)
+        );
+    }
+}
+
+/// Key of every family with its general formula
+pub fn print_families() {
+    for family in Family::ALL {
+        println!("{}: {}", family.key(), family.formula());
+    }
+}
+
+/// Same rows as [`print_points`] with full precision
+pub fn print_points_csv(points: &[(usize, &(Point, TNumber, TNumber))]) {
+    println!("index,x,y,phi,epsilon");
+    for &(index, (point, phi, epsilon)) in points {
+        println!("{},{},{},{},{}", index + 1, point.x, point.y, phi, epsilon);
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    function: String,
+    formula: String,
+    coefficients: Coefficients,
+    standard_deviation: TNumber,
+    r_squared: TNumber,
+    points: Vec<ReportRow>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    predictions: Vec<Prediction>,
+}
+
+#[derive(Serialize)]
+struct Prediction {
+    x: TNumber,
+    phi: TNumber,
+}
+
+#[derive(Serialize)]
+struct ReportRow {
+    x: TNumber,
+    y: TNumber,
+    phi: TNumber,
+    epsilon: TNumber,
+}
+
+/// Input points next to their smoothed y
+pub fn print_smoothed(
+    points: &[Point],
+    smoothed: &[Point],
+    config: &RunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let &RunConfig {
+        output,
+        precision,
+        scientific,
+        ascii,
+        ..
+    } = config;
+    match output {
+        OutputFormat::Table => {
+            use cli_table::Table;
+            let table = points
+                .iter()
+                .zip(smoothed)
+                .enumerate()
+                .map(|(index, (point, smoothed))| {
+                    vec![
+                        (index + 1).to_string(),
+                        number(point.x, precision, scientific),
+                        number(point.y, precision, scientific),
+                        number(smoothed.y, precision, scientific),
+                    ]
+                })
+                .table()
+                .border(table_border(ascii))
+                .separator(table_separator(ascii))
+                .title(["Point number", "X", "Y", "Smoothed Y"])
+                .display()?;
+            println!("{table}");
+        }
+        OutputFormat::Csv | OutputFormat::Quiet => {
+            println!("x,y,smoothed");
+            for (point, smoothed) in points.iter().zip(smoothed) {
+                println!("{},{},{}", point.x, point.y, smoothed.y);
+            }
+        }
+        OutputFormat::Json => {
+            let rows: Vec<_> = points
+                .iter()
+                .zip(smoothed)
+                .map(|(point, smoothed)| SmoothedRow {
+                    x: point.x,
+                    y: point.y,
+                    smoothed: smoothed.y,
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SmoothedRow {
+    x: TNumber,
+    y: TNumber,
+    smoothed: TNumber,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    #[serde(flatten)]
+    coefficients: Coefficients,
+    formula: String,
+}
+
+/// Coefficients as a single line of JSON,
+/// e.g. `{"family":"linear","a":2.0,"b":1.0,"formula":"2.0000*x + 1.0000"}`.
+/// Coefficients keep full precision, only the formula follows `style`
+pub fn print_coefficients(
+    function: &dyn Function,
+    style: NumberStyle,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let summary = Summary {
+        coefficients: function.coefficients(),
+        formula: function.formula(style),
+    };
+    println!("{}", serde_json::to_string(&summary)?);
+    Ok(())
+}
+
+pub fn print_json(
+    points: &[Point],
+    function: &dyn Function,
+    standard_deviation: TNumber,
+    deviations: &[(Point, TNumber, TNumber)],
+    predict: &[TNumber],
+    style: NumberStyle,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report = Report {
+        function: function_name(function),
+        formula: function.formula(style),
+        coefficients: function.coefficients(),
+        standard_deviation,
+        r_squared: function.r_squared(points),
+        points: deviations
+            .iter()
+            .map(|&(point, phi, epsilon)| ReportRow {
+                x: point.x,
+                y: point.y,
+                phi,
+                epsilon,
+            })
+            .collect(),
+        predictions: predict
+            .iter()
+            .map(|&x| Prediction {
+                x,
+                phi: function.compute(x),
+            })
+            .collect(),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// Samples of the smooth curve in the second gnuplot block
+const DUMP_CURVE_STEPS: usize = 500;
+
+/// Writes `x y phi epsilon` at the original points and then, after two blank lines
+/// so gnuplot sees it as `index 1`, the densely sampled curve `x phi`
+pub fn write_gnuplot(
+    path: &Path,
+    function: &dyn Function,
+    deviations: &[(Point, TNumber, TNumber)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::create(path)
+        .map_err(|error| format!("cannot write `{}`: {}", path.display(), error))?;
+
+    writeln!(file, "# {}", function_name(function))?;
+    writeln!(file, "# x y phi epsilon")?;
+    for (point, phi, epsilon) in deviations {
+        writeln!(file, "{} {} {} {}", point.x, point.y, phi, epsilon)?;
+    }
+
+    let (min, max) = deviations.iter().fold(
+        (TNumber::INFINITY, TNumber::NEG_INFINITY),
+        |(min, max), (point, _, _)| (min.min(point.x), max.max(point.x)),
+    );
+    writeln!(file, "\n\n# x phi")?;
+    for (x, phi) in function.sample(min..max, DUMP_CURVE_STEPS) {
+        writeln!(file, "{} {}", x, phi)?;
+    }
+
+    Ok(())
+}
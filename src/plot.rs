@@ -0,0 +1,677 @@
+use std::{
+    error::Error,
+    io::IsTerminal,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use comp_math_lab4::{Function, Point, TNumber};
+use plotters::{
+    coord::{
+        combinators::{IntoLogRange, LogCoord},
+        ranged1d::ValueFormatter,
+        types::RangedCoordf64,
+        Shift,
+    },
+    prelude::*,
+};
+
+/// Image file format
+#[derive(Clone, Copy, Debug, Default)]
+pub enum PlotFormat {
+    #[default]
+    Png,
+    /// Vector image, stays sharp when zoomed
+    Svg,
+}
+
+impl PlotFormat {
+    /// Default image path for the format
+    pub fn default_path(self) -> PathBuf {
+        match self {
+            PlotFormat::Png => PathBuf::from("./plot.png"),
+            PlotFormat::Svg => PathBuf::from("./plot.svg"),
+        }
+    }
+}
+
+impl FromStr for PlotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(PlotFormat::Png),
+            "svg" => Ok(PlotFormat::Svg),
+            _ => Err(format!(
+                "unknown plot format `{}`, expected one of: png, svg",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlotOptions {
+    pub path: PathBuf,
+    pub format: PlotFormat,
+    pub size: PlotSize,
+    /// Logarithmic x axis, points need positive x
+    pub log_x: bool,
+    /// Logarithmic y axis, points need positive y
+    pub log_y: bool,
+    /// Radius of point markers in pixels
+    pub point_size: u32,
+    /// The best curve is drawn in this color instead of its palette color,
+    /// see [`Series::best`]
+    pub curve_color: Option<PlotColor>,
+    /// Curve line width in pixels
+    pub curve_width: u32,
+    /// Fraction of the data range left empty on both sides of each axis
+    pub coord_margin: TNumber,
+    /// Points and functions are given as x = f(y), they are drawn back
+    /// with x horizontal. [`PlotOptions::log_x`] still refers to the horizontal axis
+    pub swap_axes: bool,
+    /// Family of axis and legend labels, see [`font_available`]
+    pub font: String,
+    /// Label size in pixels
+    pub font_size: u32,
+    /// Axis labels and the legend are drawn, without them no font is needed
+    pub labels: bool,
+    /// Larger datasets are drawn as an evenly spaced subset of this many points,
+    /// fitting still uses all of them
+    pub max_points: Option<usize>,
+    /// Percentage of drawn curves is shown on stderr when it is a terminal
+    pub progress: bool,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        PlotOptions {
+            path: PlotFormat::default().default_path(),
+            format: PlotFormat::default(),
+            size: PlotSize(1920, 1080),
+            log_x: false,
+            log_y: false,
+            point_size: 10,
+            curve_color: None,
+            curve_width: 3,
+            coord_margin: COORD_MARGIN_PERSENT,
+            swap_axes: false,
+            progress: false,
+            max_points: None,
+            font: "noto sans".to_owned(),
+            font_size: 16,
+            labels: true,
+        }
+    }
+}
+
+/// Image size in pixels, parsed from `WIDTHxHEIGHT`
+#[derive(Clone, Copy, Debug)]
+pub struct PlotSize(pub u32, pub u32);
+
+impl FromStr for PlotSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "invalid plot size `{}`, expected WIDTHxHEIGHT like 1920x1080",
+                s
+            )
+        };
+
+        let (width, height) = s.split_once('x').ok_or_else(error)?;
+        let width: u32 = width.parse().map_err(|_| error())?;
+        let height: u32 = height.parse().map_err(|_| error())?;
+        if width == 0 || height == 0 {
+            return Err(error());
+        }
+
+        Ok(PlotSize(width, height))
+    }
+}
+
+impl From<PlotSize> for (u32, u32) {
+    fn from(PlotSize(width, height): PlotSize) -> Self {
+        (width, height)
+    }
+}
+
+/// Color parsed from a name like `red` or hex `#ff8800`
+#[derive(Clone, Copy, Debug)]
+pub struct PlotColor(pub u8, pub u8, pub u8);
+
+impl FromStr for PlotColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "invalid color `{}`, expected hex like #ff8800 or one of: \
+                 black, white, red, green, blue, cyan, magenta, yellow",
+                s
+            )
+        };
+
+        let color = match s.to_lowercase().as_str() {
+            "black" => PlotColor(0, 0, 0),
+            "white" => PlotColor(255, 255, 255),
+            "red" => PlotColor(255, 0, 0),
+            "green" => PlotColor(0, 255, 0),
+            "blue" => PlotColor(0, 0, 255),
+            "cyan" => PlotColor(0, 255, 255),
+            "magenta" => PlotColor(255, 0, 255),
+            "yellow" => PlotColor(255, 255, 0),
+            _ => {
+                let hex = s.strip_prefix('#').ok_or_else(error)?;
+                if hex.len() != 6 || !hex.is_ascii() {
+                    return Err(error());
+                }
+                let channel = |index: usize| {
+                    u8::from_str_radix(&hex[index..index + 2], 16).map_err(|_| error())
+                };
+                PlotColor(channel(0)?, channel(2)?, channel(4)?)
+            }
+        };
+
+        Ok(color)
+    }
+}
+
+impl From<PlotColor> for RGBColor {
+    fn from(PlotColor(red, green, blue): PlotColor) -> Self {
+        RGBColor(red, green, blue)
+    }
+}
+
+/// Used instead of a [`PlotOptions::font`] that can not be loaded,
+/// when it can't be loaded either [`PlotOptions::labels`] are turned off
+pub const FALLBACK_FONT: &str = "sans-serif";
+
+/// Whether labels can be drawn with the font, without fontconfig an unknown name fails to load
+pub fn font_available(name: &str) -> bool {
+    FontDesc::new(FontFamily::Name(name), 16., FontStyle::Normal)
+        .box_size("0")
+        .is_ok()
+}
+
+/// Ranges narrower than this are treated as a single value
+const MIN_SPAN: f64 = 1e-9;
+
+/// Expands a degenerate `value..value` range symmetrically,
+/// zero width ranges break the chart
+fn with_min_span(range: Range<f64>) -> Range<f64> {
+    if range.end - range.start >= MIN_SPAN {
+        return range;
+    }
+
+    let center = (range.start + range.end) / 2.;
+    let half_span = center.abs().max(1.) / 2.;
+    (center - half_span)..(center + half_span)
+}
+
+fn with_coord_margin(range: Range<f64>, margin_persents: f64) -> Range<f64> {
+    let length = range.end - range.start;
+    let margin = length * margin_persents;
+    (range.start - margin)..(range.end + margin)
+}
+
+const MARGINS: i32 = 10;
+/// Default of [`PlotOptions::coord_margin`]
+const COORD_MARGIN_PERSENT: TNumber = 0.05;
+/// Largest accepted [`PlotOptions::coord_margin`], the data would take a third of the axis
+pub const MAX_COORD_MARGIN: TNumber = 1.;
+
+/// Smallest and largest value, expanded with [`with_min_span`]
+fn value_range(values: impl Iterator<Item = TNumber>) -> Range<TNumber> {
+    let (min, max) = values.fold(
+        (TNumber::INFINITY, TNumber::NEG_INFINITY),
+        |(min, max), value| (min.min(value), max.max(value)),
+    );
+    assert!(min <= max, "At least one point present");
+
+    with_min_span(min..max)
+}
+
+/// Linear axis with `margin` fraction of the range added on both sides
+fn linear_axis(range: Range<TNumber>, margin: TNumber) -> RangedCoordf64 {
+    with_coord_margin(range, margin).into()
+}
+
+/// Logarithmic axis, the margin is added in log space so bounds stay positive
+fn log_axis(
+    range: Range<TNumber>,
+    axis: &str,
+    margin: TNumber,
+) -> Result<LogCoord<TNumber>, String> {
+    if range.start <= 0. {
+        return Err(format!(
+            "logarithmic {} axis needs positive values, smallest is {}",
+            axis, range.start
+        ));
+    }
+
+    let log_range = with_coord_margin(range.start.ln()..range.end.ln(), margin);
+    Ok((log_range.start.exp()..log_range.end.exp())
+        .log_scale()
+        .into())
+}
+
+type Chart<'a, DB, X, Y> = ChartContext<'a, DB, Cartesian2d<X, Y>>;
+
+/// Chart with margins, labeled axes and mesh shared by every image
+fn configure_chart<'a, DB, X, Y>(
+    root: &'a DrawingArea<DB, Shift>,
+    x_axis: X,
+    y_axis: Y,
+    x_desc: &str,
+    y_desc: &str,
+    options: &PlotOptions,
+) -> Result<Chart<'a, DB, X, Y>, Box<dyn Error>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    X: Ranged<ValueType = TNumber> + ValueFormatter<TNumber>,
+    Y: Ranged<ValueType = TNumber> + ValueFormatter<TNumber>,
+{
+    let mut chart = ChartBuilder::on(root)
+        .margin(MARGINS * 2)
+        .x_label_area_size(20)
+        .y_label_area_size(40)
+        .build_cartesian_2d(x_axis, y_axis)?;
+
+    if options.labels {
+        chart
+            .configure_mesh()
+            .label_style((options.font.as_str(), options.font_size))
+            .x_labels(5)
+            .y_labels(5)
+            .x_desc(x_desc)
+            .y_desc(y_desc)
+            .draw()?;
+    } else {
+        chart.configure_mesh().x_labels(0).y_labels(0).draw()?;
+    }
+
+    Ok(chart)
+}
+
+/// Points with the functions fitted to them, `labels[i]` names `functions[i]` in the legend
+pub struct Series<'a> {
+    pub points: &'a [Point],
+    pub functions: &'a [Box<dyn Function>],
+    pub labels: &'a [String],
+    /// Index into `functions` of the curve [`PlotOptions::curve_color`] applies to
+    pub best: Option<usize>,
+}
+
+/// Draws points and every function, `labels[i]` names `functions[i]` in the legend
+/// and `functions[best]` is drawn in [`PlotOptions::curve_color`] when set
+pub fn plot(
+    points: &[Point],
+    functions: &[Box<dyn Function>],
+    labels: &[String],
+    best: usize,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    let series = Series {
+        points,
+        functions,
+        labels,
+        best: Some(best),
+    };
+    render(&[series], false, options)
+}
+
+/// Draws several datasets on one chart, points of each share the color of its first curve
+pub fn plot_comparison(series: &[Series], options: &PlotOptions) -> Result<(), Box<dyn Error>> {
+    render(series, true, options)
+}
+
+fn render(
+    series: &[Series],
+    colored_points: bool,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    let size = options.size.into();
+    match options.format {
+        PlotFormat::Png => draw_plot(
+            BitMapBackend::new(&options.path, size).into_drawing_area(),
+            series,
+            colored_points,
+            options,
+        )?,
+        PlotFormat::Svg => draw_plot(
+            SVGBackend::new(&options.path, size).into_drawing_area(),
+            series,
+            colored_points,
+            options,
+        )?,
+    }
+
+    Ok(())
+}
+
+fn draw_plot<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    series: &[Series],
+    colored_points: bool,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let points = || series.iter().flat_map(|series| series.points);
+    let x_range = value_range(points().map(|point| point.x));
+    let y_range = value_range(points().map(|point| point.y));
+
+    root.fill(&WHITE)?;
+    let root = root.margin(MARGINS, MARGINS, MARGINS, MARGINS);
+
+    let curves = Curves {
+        series,
+        colored_points,
+        x_range: x_range.clone(),
+        options,
+    };
+    let (x_range, y_range) = if options.swap_axes {
+        (y_range, x_range)
+    } else {
+        (x_range, y_range)
+    };
+    let margin = options.coord_margin;
+    match (options.log_x, options.log_y) {
+        (false, false) => curves.draw(configure_chart(
+            &root,
+            linear_axis(x_range, margin),
+            linear_axis(y_range, margin),
+            "X",
+            "Y",
+            options,
+        )?)?,
+        (true, false) => curves.draw(configure_chart(
+            &root,
+            log_axis(x_range, "x", margin)?,
+            linear_axis(y_range, margin),
+            "X",
+            "Y",
+            options,
+        )?)?,
+        (false, true) => curves.draw(configure_chart(
+            &root,
+            linear_axis(x_range, margin),
+            log_axis(y_range, "y", margin)?,
+            "X",
+            "Y",
+            options,
+        )?)?,
+        (true, true) => curves.draw(configure_chart(
+            &root,
+            log_axis(x_range, "x", margin)?,
+            log_axis(y_range, "y", margin)?,
+            "X",
+            "Y",
+            options,
+        )?)?,
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Content of the main image, drawn the same way on linear and logarithmic axes
+struct Curves<'a> {
+    series: &'a [Series<'a>],
+    /// Black points otherwise
+    colored_points: bool,
+    x_range: Range<TNumber>,
+    options: &'a PlotOptions,
+}
+
+impl Curves<'_> {
+    fn draw<'a, DB, X, Y>(&self, mut chart: Chart<'a, DB, X, Y>) -> Result<(), Box<dyn Error>>
+    where
+        DB: DrawingBackend + 'a,
+        DB::ErrorType: 'static,
+        X: Ranged<ValueType = TNumber>,
+        Y: Ranged<ValueType = TNumber>,
+    {
+        // fixed amount instead of fixed step, so narrow ranges are not jagged
+        // and wide ones are not slow
+        const CURVE_STEPS: usize = 1000;
+
+        // axes of the function, x of the function is vertical when swapped
+        let (log_x, log_y) = if self.options.swap_axes {
+            (self.options.log_y, self.options.log_x)
+        } else {
+            (self.options.log_x, self.options.log_y)
+        };
+        let swap_axes = self.options.swap_axes;
+        let to_chart = move |(x, y): (TNumber, TNumber)| if swap_axes { (y, x) } else { (x, y) };
+
+        // on log x evenly spaced in ln(x), otherwise curves are coarse near the smallest x
+        let sample_range = if log_x {
+            self.x_range.start.ln()..self.x_range.end.ln()
+        } else {
+            self.x_range.clone()
+        };
+        let steps = CURVE_STEPS;
+        let color = |index: usize| Palette99::pick(index).to_rgba();
+        let progress = Progress::new(
+            self.options.progress,
+            self.series
+                .iter()
+                .map(|series| series.functions.len())
+                .sum(),
+        );
+        // curves are numbered across every series, so each gets its own color
+        let mut index = 0;
+        for series in self.series {
+            let point_color = if self.colored_points {
+                color(index)
+            } else {
+                BLACK.to_rgba()
+            };
+            chart.draw_series(PointSeries::<_, _, Circle<_, _>, _>::new(
+                decimated(series.points, self.options.max_points)
+                    .map(|point| to_chart((point.x, point.y))),
+                self.options.point_size,
+                point_color.filled(),
+            ))?;
+
+            for (function_index, (function, label)) in
+                series.functions.iter().zip(series.labels).enumerate()
+            {
+                let color = match self.options.curve_color {
+                    Some(color) if series.best == Some(function_index) => {
+                        RGBColor::from(color).to_rgba()
+                    }
+                    _ => color(index),
+                };
+                let width = self.options.curve_width;
+                let samples: Vec<_> = if log_x {
+                    sample_range_points(&sample_range, steps)
+                        .map(|x| (x.exp(), function.compute(x.exp())))
+                        .collect()
+                } else {
+                    function.sample(sample_range.clone(), steps)
+                };
+                // outside of the domain, e.g. ln(x) for x <= 0, the curve is split
+                // instead of joined across the gap. Log y has no place for non-positive values
+                let mut segments = samples
+                    .split(|&(_, y)| !(y.is_finite() && (!log_y || y > 0.)))
+                    .filter(|segment| !segment.is_empty());
+                let segment = |segment: &[(TNumber, TNumber)]| {
+                    LineSeries::new(
+                        segment.iter().copied().map(to_chart),
+                        color.stroke_width(width),
+                    )
+                };
+                // the first one, even if empty, carries the legend entry
+                chart
+                    .draw_series(segment(segments.next().unwrap_or_default()))?
+                    .label(label)
+                    .legend(move |(x, y)| {
+                        PathElement::new([(x, y), (x + 20, y)], color.stroke_width(width))
+                    });
+                for rest in segments {
+                    chart.draw_series(segment(rest))?;
+                }
+                index += 1;
+                progress.report(index);
+            }
+        }
+
+        if self.options.labels {
+            chart
+                .configure_series_labels()
+                .label_font((self.options.font.as_str(), self.options.font_size))
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// At most `max` items at evenly spaced indices, the first one always included
+fn decimated<T>(items: &[T], max: Option<usize>) -> impl Iterator<Item = &T> {
+    let count = max.map_or(items.len(), |max| max.min(items.len()));
+    (0..count).map(move |i| &items[i * items.len() / count])
+}
+
+/// Percentage of finished steps, rewritten in place on stderr
+struct Progress {
+    total: usize,
+    enabled: bool,
+}
+
+impl Progress {
+    /// Redirected stderr would be filled with carriage returns, so it stays silent there
+    fn new(enabled: bool, total: usize) -> Self {
+        Progress {
+            total,
+            enabled: enabled && total > 0 && std::io::stderr().is_terminal(),
+        }
+    }
+
+    fn report(&self, done: usize) {
+        if !self.enabled {
+            return;
+        }
+        // stderr is unbuffered, the line shows up without a flush
+        eprint!("\rDrawing curves: {:>3}%", done * 100 / self.total);
+        if done == self.total {
+            eprintln!();
+        }
+    }
+}
+
+/// `steps + 1` evenly spaced values including both ends
+fn sample_range_points(range: &Range<TNumber>, steps: usize) -> impl Iterator<Item = TNumber> + '_ {
+    let step = (range.end - range.start) / steps.max(1) as TNumber;
+    (0..=steps).map(move |i| range.start + step * i as TNumber)
+}
+
+/// Draws residuals `ε` against x with a zero line, the usual check of fit adequacy
+pub fn plot_residuals(
+    deviations: &[(Point, TNumber, TNumber)],
+    path: &Path,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    let size = options.size.into();
+    match options.format {
+        PlotFormat::Png => draw_residuals(
+            BitMapBackend::new(path, size).into_drawing_area(),
+            deviations,
+            options,
+        )?,
+        PlotFormat::Svg => draw_residuals(
+            SVGBackend::new(path, size).into_drawing_area(),
+            deviations,
+            options,
+        )?,
+    }
+
+    Ok(())
+}
+
+fn draw_residuals<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    deviations: &[(Point, TNumber, TNumber)],
+    options: &PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let x_range = value_range(deviations.iter().map(|(point, _, _)| point.x));
+    // zero line must be visible even when every residual has the same sign
+    let y_range = value_range(
+        deviations
+            .iter()
+            .map(|(_, _, epsilon)| *epsilon)
+            .chain([0.]),
+    );
+
+    root.fill(&WHITE)?;
+    let root = root.margin(MARGINS, MARGINS, MARGINS, MARGINS);
+
+    let mut chart = configure_chart(
+        &root,
+        linear_axis(x_range.clone(), options.coord_margin),
+        linear_axis(y_range, options.coord_margin),
+        // residuals stay over the argument of the function, y when swapped
+        if options.swap_axes { "Y" } else { "X" },
+        "ε",
+        options,
+    )?;
+    chart.draw_series(LineSeries::new(
+        [(x_range.start, 0.), (x_range.end, 0.)],
+        BLACK.stroke_width(1),
+    ))?;
+    chart.draw_series(PointSeries::<_, _, Circle<_, _>, _>::new(
+        decimated(deviations, options.max_points).map(|(point, _, epsilon)| (point.x, *epsilon)),
+        options.point_size,
+        RED.filled(),
+    ))?;
+
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use comp_math_lab4::methods::{AnyFunction, Coefficients};
+
+    use super::*;
+
+    #[test]
+    fn plot_without_labels_draws_no_text() {
+        let path = std::env::temp_dir().join(format!("unlabeled_{}.svg", std::process::id()));
+        let points: Vec<_> = (1..=5)
+            .map(|x| Point {
+                x: x as TNumber,
+                y: 2. * x as TNumber,
+                w: 1.,
+            })
+            .collect();
+        let functions: Vec<Box<dyn Function>> = vec![Box::new(
+            AnyFunction::try_from(Coefficients::Linear { a: 2., b: 0. }).unwrap(),
+        )];
+        let options = PlotOptions {
+            path: path.clone(),
+            format: PlotFormat::Svg,
+            font: "no such font".to_owned(),
+            labels: false,
+            ..PlotOptions::default()
+        };
+
+        plot(&points, &functions, &["Linear".to_owned()], 0, &options).unwrap();
+        let image = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!image.contains("<text"), "{}", image);
+    }
+}
@@ -0,0 +1,53 @@
+use comp_math_lab4::{Point, TNumber};
+
+/// Multiplier of the generating line `y = TRUE_A*x + TRUE_B`
+pub const TRUE_A: TNumber = 2.;
+/// Addition of the generating line
+pub const TRUE_B: TNumber = 1.;
+/// Standard deviation of the gaussian noise added to y
+const NOISE: TNumber = 0.5;
+/// Points are spread evenly over `0..=X_SPAN`
+const X_SPAN: TNumber = 10.;
+/// Fixed so runs are reproducible
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Small xorshift generator, good enough for demo noise
+struct Xorshift(u64);
+
+impl Xorshift {
+    /// Uniform in `(0, 1]`
+    fn next_uniform(&mut self) -> TNumber {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        ((self.0 >> 11) + 1) as TNumber / (1u64 << 53) as TNumber
+    }
+
+    /// Standard normal via Box-Muller transform
+    fn next_gaussian(&mut self) -> TNumber {
+        let radius = (-2. * self.next_uniform().ln()).sqrt();
+        let angle = 2. * std::f64::consts::PI * self.next_uniform();
+        radius * angle.cos()
+    }
+}
+
+/// `count` noisy samples of `y = TRUE_A*x + TRUE_B`
+pub fn generate(count: usize) -> Vec<Point> {
+    let mut random = Xorshift(SEED);
+    let step = if count > 1 {
+        X_SPAN / (count - 1) as TNumber
+    } else {
+        0.
+    };
+
+    (0..count)
+        .map(|i| {
+            let x = step * i as TNumber;
+            Point {
+                x,
+                y: TRUE_A * x + TRUE_B + NOISE * random.next_gaussian(),
+                w: 1.,
+            }
+        })
+        .collect()
+}